@@ -1,27 +1,48 @@
-use crate::{calculator::Calculator, types::Token, types::Operator};
+use crate::{calculator::Calculator, types::AngleUnit, types::Integer, types::Token, types::Operator, types::WordSize};
+
+use crate::error::EvalError;
+use crate::panels;
 
 use iced::{
-    widget::{button, column, row, text_input, Button, Text},
-    Element, Length,
+    widget::{button, column, mouse_area, row, text_input, Column, Row, Text},
+    Alignment, Element, Length,
 };
 
+/// Bits the grid shows when the word is unbounded.
+const UNBOUNDED_GRID_BITS: u32 = 64;
+
+/// Cells in each row of the bit grid.
+const BITS_PER_ROW: u32 = 16;
+
 #[derive(Default)]
 pub struct BinCalcState {
     calculator: Calculator,
     display_content: String,
+    // Bit under the pointer, and the other end of the range being looked
+    // at, if one was marked.
+    hovered_bit: Option<u32>,
+    anchor_bit: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     ButtonPressed(Token),
     DisplayContentChanged(String),
+    DisplayContentSubmitted,
+    CycleOverflowPolicy,
+    CycleWordSize,
+    ToggleSigned,
+    Readout(panels::Readout),
+    ToggleBit(u32),
+    HoverBit(Option<u32>),
+    AnchorBit(u32),
 }
 
 impl BinCalcState {
     fn button<'a>(token: Token) -> Element<'a, Message> {
         let label = match token {
-            Token::Number(0) => "0".to_string(),
-            Token::Number(1) => "1".to_string(),
+            Token::Digit(0) => "0".to_string(),
+            Token::Digit(1) => "1".to_string(),
             _ => token.to_string(),
         };
         
@@ -34,63 +55,253 @@ impl BinCalcState {
 
     pub fn new() -> Self {
         BinCalcState {
-            calculator: Calculator::new().with_base(2),
+            calculator: Calculator::new()
+                .with_base(2)
+                .with_word_size(WordSize::Qword),
             display_content: String::new(),
+            hovered_bit: None,
+            anchor_bit: None,
         }
     }
 
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.calculator.set_angle_unit(unit);
+    }
+
+    pub fn expression(&self) -> Result<Vec<Token>, EvalError> {
+        self.calculator.expression()
+    }
+
+    pub fn set_expression(&mut self, expression: Result<Vec<Token>, EvalError>) {
+        self.display_content = match expression.and_then(|e| self.calculator.set_expression(e)) {
+            Ok(()) => self.calculator.get_display(),
+            Err(error) => error.to_string(),
+        };
+    }
+
+    pub fn calculator(&self) -> &Calculator {
+        &self.calculator
+    }
+
     pub fn update(&mut self, message: Message) {
         match message {
             Message::ButtonPressed(token) => {
                 if token.is_valid_for_base(2) {
                     self.calculator.add_token(token.clone());
                     if let Token::Equals = token {
-                        let result = self.calculator.evaluate();
-                        self.display_content = format!("{:b}", result);
+                        self.display_content = match self.calculator.evaluate() {
+                            Ok(result) => self.calculator.format_number(&result),
+                            Err(error) => error.to_string(),
+                        };
                     } else {
                         self.display_content = self.calculator.get_display();
                     }
                 }
             }
-            Message::DisplayContentChanged(_) => todo!(),
+            Message::DisplayContentChanged(content) => self.display_content = content,
+            Message::DisplayContentSubmitted => {
+                match self.calculator.evaluate_input(&self.display_content) {
+                    Ok(Some(result)) => self.display_content = self.calculator.format_number(&result),
+                    // A function definition stays in the display as typed.
+                    Ok(None) => {}
+                    Err(error) => self.display_content = error.to_string(),
+                }
+            }
+            Message::CycleOverflowPolicy => {
+                let policy = self.calculator.overflow_policy().next();
+                self.calculator.set_overflow_policy(policy);
+            }
+            Message::CycleWordSize => {
+                let word_size = self.calculator.word_size().next();
+                self.calculator.set_word_size(word_size);
+                self.display_content = self.calculator.get_display();
+                self.anchor_bit = None;
+            }
+            Message::ToggleSigned => {
+                let signed = !self.calculator.is_signed();
+                self.calculator.set_signed(signed);
+                self.display_content = self.calculator.get_display();
+            }
+            // Copying and switching modes are up to the application.
+            Message::Readout(_) => {}
+            Message::ToggleBit(bit) => {
+                self.calculator.toggle_bit(bit);
+                self.display_content = self.calculator.get_display();
+            }
+            Message::HoverBit(bit) => self.hovered_bit = bit,
+            Message::AnchorBit(bit) => {
+                self.anchor_bit = if self.anchor_bit == Some(bit) { None } else { Some(bit) };
+            }
         }
     }
 
-    pub fn view(&self) -> Element<Message> {
-        let space = 5;
-        column![
-            column![
-                text_input("...", &self.display_content)
-                    .padding(space)
-                    .on_input(Message::DisplayContentChanged),
+    // The bits of the operand that a press would flip, as many as the word
+    // has and most significant first; all zeros where a new operand is
+    // expected. Pressing a bit flips it; right-pressing one marks the end of
+    // a range, and the field from there to the pointer is shown below the
+    // grid. After a closing parenthesis and the like there is nothing to
+    // flip, so the grid shows the number on the display and cannot be
+    // pressed.
+    fn bit_grid(&self) -> Element<'_, Message> {
+        let width = self.calculator.word_size().bits().unwrap_or(UNBOUNDED_GRID_BITS);
+        let mask = (Integer::from(1) << width) - 1;
+        let operand = self.calculator.bit_operand();
+        let editable = operand.is_some();
+        let pattern = operand
+            .or_else(|| {
+                self.calculator
+                    .shown_value()
+                    .filter(|n| n.is_integer())
+                    .map(|n| n.to_integer())
+            })
+            .map(|n| n & &mask)
+            .unwrap_or_default();
+        let selection = self.selection();
+
+        let cell = |bit: u32| -> Element<'_, Message> {
+            let selected = selection.is_some_and(|(high, low)| low <= bit && bit <= high);
+            let label = column![
+                Text::new(bit.to_string()).size(9),
+                Text::new(if pattern.bit(bit as u64) { "1" } else { "0" }),
             ]
-            .padding(15),
+            .align_x(Alignment::Center);
+            mouse_area(
+                button(label)
+                    .on_press_maybe(editable.then_some(Message::ToggleBit(bit)))
+                    .style(if selected { button::primary } else { button::secondary })
+                    .width(Length::Fill)
+                    .padding(2),
+            )
+            .on_enter(Message::HoverBit(Some(bit)))
+            .on_right_press(Message::AnchorBit(bit))
+            .into()
+        };
+        let per_row = BITS_PER_ROW.min(width);
+        let rows = (0..width / per_row).rev().map(|row| {
+            let bits = (row * per_row..(row + 1) * per_row).rev();
+            Row::with_children(bits.map(cell)).spacing(2).into()
+        });
+
+        let status = match selection {
+            Some((high, low)) => {
+                let field = (&pattern >> low) & ((Integer::from(1) << (high - low + 1)) - 1);
+                if high == low {
+                    format!("Bit {} = {}", high, field)
+                } else {
+                    format!("Bits {}..{} = 0x{:X} = {}", high, low, field, field)
+                }
+            }
+            None => "Click a bit to flip it; right-click one to mark the end of a range".to_string(),
+        };
+        column![
+            mouse_area(Column::with_children(rows).spacing(2)).on_exit(Message::HoverBit(None)),
+            Text::new(status).size(12),
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    // The bits from the marked one to the one under the pointer, highest
+    // first.
+    fn selection(&self) -> Option<(u32, u32)> {
+        let hovered = self.hovered_bit.or(self.anchor_bit)?;
+        let anchor = self.anchor_bit.unwrap_or(hovered);
+        Some((hovered.max(anchor), hovered.min(anchor)))
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let space = 5;
+        row![
             column![
-                row![
-                    BinCalcState::button(Token::ClearScreen),
-                    BinCalcState::button(Token::LeftParenthesis),
-                    BinCalcState::button(Token::RightParenthesis),
-                    BinCalcState::button(Token::Operator(Operator::Division)),
-                    BinCalcState::button(Token::ClearToken),
+                column![
+                    text_input("...", &self.display_content)
+                        .padding(space)
+                        .on_input(Message::DisplayContentChanged)
+                        .on_submit(Message::DisplayContentSubmitted),
+                    row![
+                        button(Text::new(format!(
+                            "Overflow: {}",
+                            self.calculator.overflow_policy()
+                        )))
+                        .on_press(Message::CycleOverflowPolicy)
+                        .padding(space),
+                        button(Text::new(self.calculator.word_size().to_string()))
+                            .on_press(Message::CycleWordSize)
+                            .padding(space),
+                        button(Text::new(if self.calculator.is_signed() {
+                            "Signed"
+                        } else {
+                            "Unsigned"
+                        }))
+                        .on_press(Message::ToggleSigned)
+                        .padding(space),
+                    ]
+                    .spacing(space),
+                    panels::readout(&self.calculator, Message::Readout),
+                    self.bit_grid(),
                 ]
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    BinCalcState::button(Token::Number(0)),
-                    BinCalcState::button(Token::Number(1)),
-                    BinCalcState::button(Token::Operator(Operator::Multiplication)),
-                    BinCalcState::button(Token::Operator(Operator::Subtraction))
+                .spacing(space)
+                .padding(15),
+                column![
+                    row![
+                        BinCalcState::button(Token::ClearScreen),
+                        BinCalcState::button(Token::LeftParenthesis),
+                        BinCalcState::button(Token::RightParenthesis),
+                        BinCalcState::button(Token::Operator(Operator::Division)),
+                        BinCalcState::button(Token::ClearToken),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        BinCalcState::button(Token::Operator(Operator::Remainder)),
+                        BinCalcState::button(Token::Operator(Operator::Modulo)),
+                        BinCalcState::button(Token::Operator(Operator::EuclideanDivision)),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        BinCalcState::button(Token::Operator(Operator::And)),
+                        BinCalcState::button(Token::Operator(Operator::Or)),
+                        BinCalcState::button(Token::Operator(Operator::Xor)),
+                        BinCalcState::button(Token::Not),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        BinCalcState::button(Token::Operator(Operator::Nand)),
+                        BinCalcState::button(Token::Operator(Operator::Nor)),
+                        BinCalcState::button(Token::Operator(Operator::RotateLeft)),
+                        BinCalcState::button(Token::Operator(Operator::RotateRight)),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        BinCalcState::button(Token::Operator(Operator::ShiftLeft)),
+                        BinCalcState::button(Token::Operator(Operator::LogicalShiftRight)),
+                        BinCalcState::button(Token::Operator(Operator::ArithmeticShiftRight)),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        BinCalcState::button(Token::Digit(0)),
+                        BinCalcState::button(Token::Digit(1)),
+                        BinCalcState::button(Token::Operator(Operator::Multiplication)),
+                        BinCalcState::button(Token::Operator(Operator::Subtraction))
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        BinCalcState::button(Token::ToggleSign),
+                        BinCalcState::button(Token::Operator(Operator::Addition)),
+                        BinCalcState::button(Token::Operator(Operator::Power)),
+                        BinCalcState::button(Token::Equals)
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
                 ]
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    BinCalcState::button(Token::Operator(Operator::Addition)),
-                    BinCalcState::button(Token::Equals)
-                ]
-                .spacing(space * 2)
-                .padding(space),
-            ]
-            .padding(10)
+                .padding(10)
+            ],
+            panels::variables(&self.calculator, Message::ButtonPressed),
         ]
         .into()
     }
@@ -1,19 +1,94 @@
 // calculator.rs
-use crate::types::{Operator, Token};
+use std::collections::BTreeMap;
+
+use num_traits::{Euclid, One, Signed, ToPrimitive, Zero};
+
+use crate::complex_number::Complex;
+use crate::constants::Constant;
+use crate::error::EvalError;
+use crate::functions;
+use crate::lexer;
+use crate::parser::{self, Expr, Statement};
+use crate::types::{
+    AngleUnit, ArithmeticMode, ComplexFormat, Function, Integer, Operator, OverflowPolicy, RationalFormat, RoundingMode, Token,
+    UnaryOperator, Value, WordSize,
+};
+
+/// How many decimal digits a value may grow to before evaluation gives up,
+/// so that a runaway expression cannot exhaust memory.
+pub const DEFAULT_DIGIT_LIMIT: u64 = 10_000;
+
+/// Significant digits kept by decimal arithmetic unless chosen otherwise.
+pub const DEFAULT_PRECISION: u32 = 50;
+
+/// Variable that always holds the last result.
+const ANS: &str = "ans";
+
+/// Name of the imaginary unit in complex mode.
+const IMAGINARY_UNIT: &str = "i";
+
+/// How deeply user functions may call one another before evaluation gives
+/// up, since a function that calls itself never stops.
+const MAX_CALL_DEPTH: usize = 100;
+
+/// Digits after the point shown for a fraction written out as a decimal.
+const MAX_FRACTION_DIGITS: usize = 64;
+
+/// A function defined as `name(parameters) = body`, kept with the text it
+/// was typed as.
+#[derive(Debug, Clone)]
+pub struct UserFunction {
+    pub parameters: Vec<String>,
+    pub text: String,
+    body: Expr,
+}
+
+// Arguments of the user function being evaluated, by parameter name, and
+// how many calls deep it is.
+struct Scope<'a, T = Value> {
+    arguments: BTreeMap<&'a str, T>,
+    depth: usize,
+}
+
+impl<T> Default for Scope<'_, T> {
+    fn default() -> Self {
+        Scope { arguments: BTreeMap::new(), depth: 0 }
+    }
+}
 
 #[derive(Default)]
 pub struct Calculator {
     pub token_stream: Vec<Token>,
-    num: i64,
+    num: Integer,
+    // Digits typed after the decimal point, once it has been pressed.
+    decimals: Option<u32>,
+    negative: bool,
     number_was_pressed: bool,
     parentheses_opened: bool,
     base: u8,
+    word_size: WordSize,
+    signed: bool,
+    overflow_policy: OverflowPolicy,
+    digit_limit: u64,
+    arithmetic: ArithmeticMode,
+    rational_format: RationalFormat,
+    precision: u32,
+    rounding: RoundingMode,
+    angle_unit: AngleUnit,
+    variables: BTreeMap<String, Value>,
+    user_functions: BTreeMap<String, UserFunction>,
+    // Values kept by complex mode, which may not be real.
+    complex_variables: BTreeMap<String, Complex>,
+    complex_format: ComplexFormat,
 }
 
 impl Calculator {
     pub fn new() -> Self {
         Calculator {
             base: 10,
+            signed: true,
+            digit_limit: DEFAULT_DIGIT_LIMIT,
+            precision: DEFAULT_PRECISION,
             ..Default::default()
         }
     }
@@ -23,37 +98,204 @@ impl Calculator {
         self
     }
 
+    pub fn base(&self) -> u8 {
+        self.base
+    }
+
+    /// Switches to another base from 2 to 36. A number being typed is
+    /// finished first, so that it keeps its value rather than its digits.
+    pub fn set_base(&mut self, base: u8) {
+        if self.number_was_pressed {
+            self.commit_number();
+        }
+        self.base = base;
+    }
+
+    pub fn with_word_size(mut self, word_size: WordSize) -> Self {
+        self.word_size = word_size;
+        self
+    }
+
+    pub fn with_arithmetic(mut self, arithmetic: ArithmeticMode) -> Self {
+        self.arithmetic = arithmetic;
+        self
+    }
+
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow_policy = policy;
+    }
+
+    pub fn word_size(&self) -> WordSize {
+        self.word_size
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signed || self.word_size == WordSize::Unbounded
+    }
+
+    pub fn digit_limit(&self) -> u64 {
+        self.digit_limit
+    }
+
+    pub fn set_digit_limit(&mut self, digit_limit: u64) {
+        self.digit_limit = digit_limit;
+    }
+
+    pub fn arithmetic(&self) -> ArithmeticMode {
+        self.arithmetic
+    }
+
+    /// Switches arithmetic. Going to integers truncates any fractions
+    /// already in the expression toward zero.
+    pub fn set_arithmetic(&mut self, arithmetic: ArithmeticMode) {
+        self.arithmetic = arithmetic;
+        if arithmetic == ArithmeticMode::Integer {
+            for token in &mut self.token_stream {
+                if let Token::Number(n) = token {
+                    *n = Value::from(n.to_integer());
+                }
+            }
+            if let Some(places) = self.decimals.take() {
+                self.num /= Integer::from(10).pow(places);
+            }
+        }
+    }
+
+    pub fn rational_format(&self) -> RationalFormat {
+        self.rational_format
+    }
+
+    pub fn set_rational_format(&mut self, format: RationalFormat) {
+        self.rational_format = format;
+    }
+
+    pub fn precision(&self) -> u32 {
+        self.precision
+    }
+
+    pub fn set_precision(&mut self, precision: u32) {
+        self.precision = precision;
+    }
+
+    pub fn rounding(&self) -> RoundingMode {
+        self.rounding
+    }
+
+    pub fn set_rounding(&mut self, rounding: RoundingMode) {
+        self.rounding = rounding;
+    }
+
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.angle_unit = unit;
+    }
+
+    /// Variables by name, `ans` among them once anything has been evaluated.
+    pub fn variables(&self) -> &BTreeMap<String, Value> {
+        &self.variables
+    }
+
+    pub fn user_functions(&self) -> &BTreeMap<String, UserFunction> {
+        &self.user_functions
+    }
+
+    pub fn complex_variables(&self) -> &BTreeMap<String, Complex> {
+        &self.complex_variables
+    }
+
+    pub fn complex_format(&self) -> ComplexFormat {
+        self.complex_format
+    }
+
+    pub fn set_complex_format(&mut self, format: ComplexFormat) {
+        self.complex_format = format;
+    }
+
+    /// Changes the word and truncates everything already entered to it.
+    pub fn set_word_size(&mut self, word_size: WordSize) {
+        self.word_size = word_size;
+        self.truncate_to_word();
+    }
+
+    pub fn set_signed(&mut self, signed: bool) {
+        self.signed = signed;
+        self.truncate_to_word();
+    }
+
+    fn truncate_to_word(&mut self) {
+        let (word_size, signed) = (self.word_size, self.is_signed());
+        for token in &mut self.token_stream {
+            if let Token::Number(n) = token
+                && n.is_integer()
+            {
+                *n = Value::from(word_size.wrap(n.numer(), signed));
+            }
+        }
+        self.num = if self.base == 10 {
+            word_size.wrap(&self.num, signed)
+        } else {
+            word_size.wrap(&self.num, false)
+        };
+        for value in self.variables.values_mut() {
+            if value.is_integer() {
+                *value = Value::from(word_size.wrap(value.numer(), signed));
+            }
+        }
+    }
+
     pub fn add_token(&mut self, token: Token) {
         match token {
-            Token::Number(n) => {
-                if n < self.base as i64 {
-                    self.num = self.num * self.base as i64 + n;
+            Token::Digit(d) => {
+                if d < self.base {
+                    // A digit that would overflow under the checked policy is
+                    // simply not accepted, like on a desk calculator.
+                    let entry = &self.num * self.base + d;
+                    if let Some(places) = self.decimals {
+                        if self.check_digit_limit(entry.bits()).is_ok() {
+                            self.num = entry;
+                            self.decimals = Some(places + 1);
+                        }
+                    } else if let Ok(num) = self.fit_entry(entry) {
+                        self.num = num;
+                        self.number_was_pressed = true;
+                    }
+                }
+            }
+            Token::DecimalPoint => {
+                if self.base == 10
+                    && self.arithmetic != ArithmeticMode::Integer
+                    && self.decimals.is_none()
+                {
+                    self.decimals = Some(0);
                     self.number_was_pressed = true;
                 }
             }
+            Token::Number(n) => {
+                if self.number_was_pressed {
+                    self.commit_number();
+                }
+                self.token_stream.push(Token::Number(n));
+            }
             Token::Operator(o) => {
                 if !self.number_was_pressed {
-                    if let Some(last) = self.token_stream.last_mut() {
-                        if let Token::Operator(op) = last {
-                            *op = o;
-                        } else {
-                            self.token_stream.push(Token::Operator(o));
-                        }
+                    if let Some(Token::Operator(op)) = self.token_stream.last_mut() {
+                        *op = o;
                     } else {
                         self.token_stream.push(Token::Operator(o));
                     }
                 } else {
-                    self.token_stream.push(Token::Number(self.num));
-                    self.num = 0;
+                    self.commit_number();
                     self.token_stream.push(Token::Operator(o));
-                    self.number_was_pressed = false;
                 }
             }
+            Token::Not => self.push_not(),
+            Token::ToggleSign => self.toggle_sign(),
             Token::LeftParenthesis => {
                 if self.number_was_pressed {
-                    self.token_stream.push(Token::Number(self.num));
-                    self.num = 0;
-                    self.number_was_pressed = false;
+                    self.commit_number();
                     self.token_stream
                         .push(Token::Operator(Operator::Multiplication));
                 }
@@ -62,23 +304,55 @@ impl Calculator {
             }
             Token::RightParenthesis => {
                 if self.number_was_pressed {
-                    self.token_stream.push(Token::Number(self.num));
-                    self.num = 0;
-                    self.number_was_pressed = false;
+                    self.commit_number();
                 }
                 self.token_stream.push(Token::RightParenthesis);
             }
+            Token::Function(function) => {
+                if self.number_was_pressed {
+                    self.commit_number();
+                    self.token_stream
+                        .push(Token::Operator(Operator::Multiplication));
+                }
+                self.token_stream.push(Token::Function(function));
+                self.token_stream.push(Token::LeftParenthesis);
+                self.parentheses_opened = true;
+            }
+            Token::Constant(_) | Token::Identifier(_) => {
+                if self.number_was_pressed {
+                    self.commit_number();
+                    self.token_stream
+                        .push(Token::Operator(Operator::Multiplication));
+                }
+                self.token_stream.push(token);
+            }
+            Token::Comma | Token::Angle(_) | Token::Percent => {
+                if self.number_was_pressed {
+                    self.commit_number();
+                }
+                self.token_stream.push(token);
+            }
             Token::ClearScreen => {
                 self.token_stream.clear();
-                self.num = 0;
+                self.num = Integer::zero();
+                self.decimals = None;
+                self.negative = false;
                 self.number_was_pressed = false;
                 self.parentheses_opened = false;
             }
             Token::ClearToken => {
                 if self.number_was_pressed {
-                    self.num /= 10;
-                    if self.num == 0 {
+                    match self.decimals {
+                        Some(0) => self.decimals = None,
+                        Some(places) => {
+                            self.num /= self.base;
+                            self.decimals = Some(places - 1);
+                        }
+                        None => self.num /= self.base,
+                    }
+                    if self.num.is_zero() && self.decimals.is_none() {
                         self.number_was_pressed = false;
+                        self.negative = false;
                     }
                 } else if !self.token_stream.is_empty() {
                     self.token_stream.pop();
@@ -86,163 +360,1652 @@ impl Calculator {
             }
             Token::Equals => {
                 if self.number_was_pressed {
-                    self.token_stream.push(Token::Number(self.num));
-                    self.num = 0;
-                    self.number_was_pressed = false;
+                    self.commit_number();
                 }
                 // Evaluation handled separately
             }
         }
     }
 
+    // In decimal the digits typed so far are the value itself. In the other
+    // bases they spell a bit pattern that may fill the whole word, so that
+    // `FF` is accepted as a signed byte and means -1.
+    fn fit_entry(&self, entry: Integer) -> Result<Integer, EvalError> {
+        if self.base == 10 {
+            return self.fit(entry);
+        }
+
+        let Some(max) = self.word_size.mask() else {
+            self.check_digit_limit(entry.bits())?;
+            return Ok(entry);
+        };
+        if entry <= max {
+            return Ok(entry);
+        }
+        match self.overflow_policy {
+            OverflowPolicy::Checked => Err(EvalError::Overflow),
+            OverflowPolicy::Wrapping => Ok(self.word_size.wrap(&entry, false)),
+            OverflowPolicy::Saturating => Ok(max),
+        }
+    }
+
+    fn entry_value(&self, entry: Integer) -> Integer {
+        if self.base == 10 {
+            entry
+        } else {
+            self.word_size.wrap(&entry, self.is_signed())
+        }
+    }
+
+    fn current_number(&self) -> Integer {
+        let value = self.entry_value(self.num.clone());
+        if self.negative {
+            self.word_size.wrap(&-value, self.is_signed())
+        } else {
+            value
+        }
+    }
+
+    /// Brings an arithmetic result into the range of the current word,
+    /// according to the overflow policy. Without a word only the digit limit
+    /// applies.
+    fn fit(&self, value: Integer) -> Result<Integer, EvalError> {
+        let Some((min, max)) = self.word_size.range(self.is_signed()) else {
+            self.check_digit_limit(value.bits())?;
+            return Ok(value);
+        };
+        if min <= value && value <= max {
+            return Ok(value);
+        }
+        match self.overflow_policy {
+            OverflowPolicy::Checked => Err(EvalError::Overflow),
+            OverflowPolicy::Wrapping => Ok(self.word_size.wrap(&value, self.is_signed())),
+            OverflowPolicy::Saturating => Ok(value.clamp(min, max)),
+        }
+    }
+
+    // The limit is counted in decimal digits but enforced on the binary size,
+    // which is cheap to read; a decimal digit is worth log2(10) bits.
+    fn bit_limit(&self) -> u64 {
+        self.digit_limit.saturating_mul(3322) / 1000 + 1
+    }
+
+    fn check_digit_limit(&self, bits: u64) -> Result<(), EvalError> {
+        if bits > self.bit_limit() {
+            Err(EvalError::DigitLimit)
+        } else {
+            Ok(())
+        }
+    }
+
+    // The entry as a value; digits after a decimal point make it a fraction
+    // over a power of ten.
+    fn current_value(&self) -> Value {
+        let Some(places) = self.decimals else {
+            return Value::from(self.current_number());
+        };
+        let value = Value::new(self.num.clone(), Integer::from(10).pow(places));
+        if self.negative { -value } else { value }
+    }
+
+    // The entry exactly as typed, keeping a trailing point or zeros.
+    fn format_entry(&self) -> String {
+        let Some(places) = self.decimals else {
+            return self.format_integer(&self.current_number());
+        };
+        let digits = format!("{:0>width$}", self.num, width = places as usize + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - places as usize);
+        let sign = if self.negative { "-" } else { "" };
+        format!("{}{}.{}", sign, whole, fraction)
+    }
+
+    fn commit_number(&mut self) {
+        self.token_stream.push(Token::Number(self.current_value()));
+        self.num = Integer::zero();
+        self.decimals = None;
+        self.negative = false;
+        self.number_was_pressed = false;
+    }
+
+    /// Flips one bit of `bit_operand`, as its word holds it. Where an
+    /// operand is expected, starts a number with just that bit set.
+    pub fn toggle_bit(&mut self, bit: u32) {
+        let Some(operand) = self.bit_operand() else {
+            return;
+        };
+        let flipped = self.word_size.wrap(&(operand ^ (Integer::one() << bit)), self.is_signed());
+        if !self.number_was_pressed && matches!(self.token_stream.last(), Some(Token::Number(_))) {
+            self.token_stream.pop();
+            self.token_stream.push(Token::Number(Value::from(flipped)));
+        } else {
+            self.set_entry(flipped);
+        }
+    }
+
+    /// The whole number that `toggle_bit` works on: the one being typed or
+    /// a finished one the expression ends on, or zero where an operand is
+    /// expected. `None` after anything else that ends a value, such as a
+    /// parenthesis, which has no bits to flip.
+    pub fn bit_operand(&self) -> Option<Integer> {
+        if self.number_was_pressed {
+            return self.decimals.is_none().then(|| self.current_number());
+        }
+        match self.token_stream.last() {
+            Some(Token::Number(n)) if n.is_integer() => Some(n.to_integer()),
+            Some(
+                Token::Number(_)
+                | Token::RightParenthesis
+                | Token::Constant(_)
+                | Token::Identifier(_)
+                | Token::Angle(_)
+                | Token::Percent,
+            ) => None,
+            _ => Some(Integer::zero()),
+        }
+    }
+
+    // Makes a value the number being typed, as if its digits had been
+    // pressed: a magnitude and a sign, or a bit pattern of the word.
+    fn set_entry(&mut self, value: Integer) {
+        if self.base == 10 || self.word_size.mask().is_none() {
+            self.negative = value.is_negative();
+            self.num = value.abs();
+        } else {
+            self.negative = false;
+            self.num = self.word_size.wrap(&value, false);
+        }
+        self.number_was_pressed = true;
+    }
+
+    // Flips the sign of whatever operand is "current": the number being
+    // entered, a finished result, or the operand that is about to be typed.
+    fn toggle_sign(&mut self) {
+        if self.number_was_pressed {
+            self.negative = !self.negative;
+            return;
+        }
+
+        let pending_negation = self.is_prefix_position(1);
+        match self.token_stream.last() {
+            Some(Token::Number(n)) => {
+                if let Ok(negated) = self.apply_unary(UnaryOperator::Negation, n.clone()) {
+                    self.token_stream.pop();
+                    self.token_stream.push(Token::Number(negated));
+                }
+            }
+            Some(Token::RightParenthesis | Token::Constant(_) | Token::Identifier(_)) => {}
+            Some(Token::Operator(Operator::Subtraction)) if pending_negation => {
+                self.token_stream.pop();
+            }
+            _ => self.token_stream.push(Token::Operator(Operator::Subtraction)),
+        }
+    }
+
+    // NOT applies to the number being entered or a finished result, and
+    // otherwise to the operand that is about to be typed.
+    fn push_not(&mut self) {
+        if self.number_was_pressed {
+            self.token_stream.push(Token::Not);
+            self.commit_number();
+        } else if let Some(Token::Number(_)) = self.token_stream.last() {
+            let index = self.token_stream.len() - 1;
+            self.token_stream.insert(index, Token::Not);
+        } else {
+            self.token_stream.push(Token::Not);
+        }
+    }
+
+    // Whether the token `back` places from the end of the stream starts an
+    // operand rather than following one.
+    fn is_prefix_position(&self, back: usize) -> bool {
+        let len = self.token_stream.len();
+        if len <= back {
+            return true;
+        }
+        matches!(
+            self.token_stream[len - back - 1],
+            Token::Operator(_) | Token::LeftParenthesis
+        )
+    }
+
     pub fn get_display(&self) -> String {
         if self.token_stream.is_empty() && !self.number_was_pressed {
             return String::new();
         }
-        
+
         let mut display = String::new();
-        
+
+        // Fractions are bracketed inside a longer expression, where `1/2`
+        // would read as a division.
+        let lone = self.token_stream.len() == 1 && !self.number_was_pressed;
+        let bracket = |n: &Value| {
+            !lone
+                && !n.is_integer()
+                && self.rational_format == RationalFormat::Fraction
+                && self.arithmetic == ArithmeticMode::Rational
+        };
+
         // Format existing tokens with current base
         for token in &self.token_stream {
             let formatted = match token {
-                Token::Number(n) => match self.base {
-                    2 => format!("{:b}", n),
-                    16 => format!("{:X}", n),
-                    _ => n.to_string(),
-                },
+                Token::Number(n) if bracket(n) => {
+                    format!("({})", self.format_number(n))
+                }
+                Token::Number(n) => self.format_number(n),
                 _ => token.to_string(),
             };
             display.push_str(&formatted);
             display.push(' ');
         }
-        
+
         // Format current number being entered
         if self.number_was_pressed {
-            let current = match self.base {
-                2 => format!("{:b}", self.num),
-                16 => format!("{:X}", self.num),
-                _ => self.num.to_string(),
-            };
-            display.push_str(&current);
+            if self.negative && self.num.is_zero() && self.base == 10 {
+                display.push('-');
+            }
+            display.push_str(&self.format_entry());
         }
-        
+
         display.trim().to_string()
     }
 
-    fn calculate_equation(&self, operators: &[Operator], numbers: &[i64]) -> i64 {
-        if numbers.is_empty() {
-            return 0;
+    /// Formats a value in this calculator's base. Negative numbers are shown
+    /// with a sign in decimal and as two's complement of the word size
+    /// everywhere else, unless the word is unbounded. Fractions are written
+    /// as chosen by the rational format, rounded like a pocket calculator
+    /// does in floating-point arithmetic, or to the precision in decimal
+    /// arithmetic.
+    pub fn format_number(&self, n: &Value) -> String {
+        match self.arithmetic {
+            ArithmeticMode::Float if self.base == 10 => return format_float(n.to_f64()),
+            ArithmeticMode::Decimal if self.base == 10 => {
+                return n.to_scientific_string(self.precision as i64);
+            }
+            _ => {}
+        }
+        if n.is_integer() {
+            return self.format_integer(n.numer());
+        }
+        match self.rational_format {
+            RationalFormat::Fraction => n.to_string(),
+            RationalFormat::Decimal => n.to_decimal_string(MAX_FRACTION_DIGITS),
+        }
+    }
+
+    fn format_integer(&self, n: &Integer) -> String {
+        self.format_in_base(n, self.base)
+    }
+
+    /// Formats a whole number in some other base as this calculator would
+    /// if it worked in that base, with the same word.
+    pub fn format_in_base(&self, n: &Integer, base: u8) -> String {
+        if base == 10 {
+            return n.to_string();
+        }
+        let digits = match self.word_size.mask() {
+            Some(mask) => (n & mask).to_str_radix(base as u32),
+            None => n.to_str_radix(base as u32),
+        };
+        digits.to_uppercase()
+    }
+
+    /// The expression entered so far, with a number still being typed
+    /// finished off, ready to be carried to another calculator. Variables go
+    /// as their values, since the other calculator has variables of its own,
+    /// and one that is not real cannot be carried at all.
+    pub fn expression(&self) -> Result<Vec<Token>, EvalError> {
+        let mut expression = Vec::new();
+        let mut tokens = self.token_stream.iter().peekable();
+        while let Some(token) = tokens.next() {
+            let call = matches!(tokens.peek(), Some(Token::LeftParenthesis));
+            expression.push(match token {
+                Token::Identifier(name) if !call => self.carried_variable(name)?,
+                _ => token.clone(),
+            });
+        }
+        if self.number_was_pressed {
+            expression.push(Token::Number(self.current_value()));
+        }
+        Ok(expression)
+    }
+
+    // A variable as the number it holds; complex mode's own values come
+    // first, as when evaluating. Names this calculator does not know are left
+    // for the other one to resolve.
+    fn carried_variable(&self, name: &str) -> Result<Token, EvalError> {
+        Ok(match (self.complex_variables.get(name), self.variables.get(name)) {
+            (Some(value), _) => Token::Number(real_value(*value)?),
+            (None, Some(value)) => Token::Number(value.clone()),
+            (None, None) => Token::Identifier(name.to_string()),
+        })
+    }
+
+    /// Replaces the expression with one carried over from another
+    /// calculator. Numbers keep their values as far as this calculator's
+    /// arithmetic and word allow: integer arithmetic truncates fractions
+    /// toward zero, and whole numbers are fitted to the word under the
+    /// overflow policy. If one does not fit, the expression is left as it
+    /// was.
+    pub fn set_expression(&mut self, mut expression: Vec<Token>) -> Result<(), EvalError> {
+        for token in &mut expression {
+            if let Token::Number(n) = token {
+                if self.arithmetic == ArithmeticMode::Integer {
+                    *n = Value::from(n.to_integer());
+                }
+                if n.is_integer() {
+                    *n = Value::from(self.fit(n.to_integer())?);
+                }
+            }
         }
+        self.add_token(Token::ClearScreen);
+        self.token_stream = expression;
+        Ok(())
+    }
 
-        let mut ops = operators.to_vec();
-        let mut nums = numbers.to_vec();
+    /// The number the display ends on: the one being typed, or else the
+    /// last one in the expression, which after `=` is the result.
+    pub fn shown_value(&self) -> Option<Value> {
+        if self.number_was_pressed {
+            return Some(self.current_value());
+        }
+        self.token_stream.iter().rev().find_map(|token| match token {
+            Token::Number(n) => Some(n.clone()),
+            _ => None,
+        })
+    }
 
-        let mut i = 0;
-        while i < ops.len() {
-            match ops[i] {
-                Operator::Multiplication => {
-                    let result = nums[i] * nums[i + 1];
-                    nums[i] = result;
-                    nums.remove(i + 1);
-                    ops.remove(i);
+    fn evaluate_expr(&self, expr: &Expr, scope: &Scope) -> Result<Value, EvalError> {
+        match expr {
+            Expr::Number(n) => self.literal(n),
+            // Parameters hide variables of the same name. Values kept from
+            // another arithmetic are taken like literals.
+            Expr::Variable(name) => match scope.arguments.get(name.as_str()) {
+                Some(value) => Ok(value.clone()),
+                None => match self.variables.get(name) {
+                    Some(value) => self.literal(value),
+                    None => Err(EvalError::UnknownName(name.clone())),
+                },
+            },
+            Expr::Unary(op, operand) => {
+                let operand = self.evaluate_expr(operand, scope)?;
+                self.apply_unary(*op, operand)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.evaluate_expr(lhs, scope)?;
+                if let Expr::Percent(percent) = rhs.as_ref() {
+                    let percent = self.evaluate_expr(percent, scope)?;
+                    return self.apply_percent(*op, lhs, percent);
+                }
+                let rhs = self.evaluate_expr(rhs, scope)?;
+                self.apply_operator(*op, lhs, rhs)
+            }
+            Expr::Percent(operand) => {
+                let operand = self.evaluate_expr(operand, scope)?;
+                self.apply_operator(Operator::Division, operand, Value::from(100))
+            }
+            // An angle given with its unit is taken in that unit rather than
+            // converted through a rounded pi, so sin(180deg) is exactly zero.
+            Expr::Call(function, arguments) => match arguments.as_slice() {
+                [Expr::Angle(unit, angle)] if function.takes_angle() => {
+                    let angle = self.evaluate_expr(angle, scope)?;
+                    self.approximate(*function, angle, *unit)
+                }
+                _ => {
+                    let arguments = self.evaluate_arguments(arguments, scope)?;
+                    self.call(*function, arguments)
                 }
-                Operator::Division => {
-                    let result = nums[i] / nums[i + 1];
-                    nums[i] = result;
-                    nums.remove(i + 1);
-                    ops.remove(i);
+            },
+            Expr::UserCall(name, arguments) => {
+                let function = self.user_function(name, arguments.len(), scope.depth)?;
+                let arguments = self.evaluate_arguments(arguments, scope)?;
+                let inner = Scope {
+                    arguments: function.parameters.iter().map(String::as_str).zip(arguments).collect(),
+                    depth: scope.depth + 1,
+                };
+                self.evaluate_expr(&function.body, &inner)
+            }
+            Expr::Constant(constant) => self.constant(constant),
+            Expr::Angle(unit, operand) => {
+                let operand = self.evaluate_expr(operand, scope)?;
+                self.convert_angle(operand, *unit)
+            }
+        }
+    }
+
+    // A percentage on the right works as on a desk calculator: 200 + 10% is
+    // 220, 200 - 10% is 180, 200 * 10% is 20 and 200 / 10% is 2000. Dividing
+    // by 100 last keeps integer arithmetic from truncating the percentage.
+    fn apply_percent(&self, op: Operator, lhs: Value, percent: Value) -> Result<Value, EvalError> {
+        let hundred = Value::from(100);
+        match op {
+            Operator::Addition | Operator::Subtraction => {
+                let part = self.apply_operator(Operator::Multiplication, lhs.clone(), percent)?;
+                let part = self.apply_operator(Operator::Division, part, hundred)?;
+                self.apply_operator(op, lhs, part)
+            }
+            Operator::Multiplication => {
+                let product = self.apply_operator(Operator::Multiplication, lhs, percent)?;
+                self.apply_operator(Operator::Division, product, hundred)
+            }
+            Operator::Division => {
+                let scaled = self.apply_operator(Operator::Multiplication, lhs, hundred)?;
+                self.apply_operator(Operator::Division, scaled, percent)
+            }
+            _ => {
+                let fraction = self.apply_operator(Operator::Division, percent, hundred)?;
+                self.apply_operator(op, lhs, fraction)
+            }
+        }
+    }
+
+    // The user function a call refers to, if it exists, takes that many
+    // arguments and is not one call too deep.
+    fn user_function(&self, name: &str, arguments: usize, depth: usize) -> Result<&UserFunction, EvalError> {
+        let function = self
+            .user_functions
+            .get(name)
+            .ok_or_else(|| EvalError::UnknownName(name.to_string()))?;
+        if arguments != function.parameters.len() {
+            return Err(EvalError::ArgumentCount(name.to_string()));
+        }
+        if depth >= MAX_CALL_DEPTH {
+            return Err(EvalError::RecursionLimit);
+        }
+        Ok(function)
+    }
+
+    fn evaluate_arguments(&self, arguments: &[Expr], scope: &Scope) -> Result<Vec<Value>, EvalError> {
+        arguments
+            .iter()
+            .map(|argument| self.evaluate_expr(argument, scope))
+            .collect()
+    }
+
+    fn literal(&self, n: &Value) -> Result<Value, EvalError> {
+        match self.arithmetic {
+            ArithmeticMode::Float => float_value(n.to_f64()),
+            ArithmeticMode::Decimal => Ok(self.round_decimal(n)),
+            _ => Ok(n.clone()),
+        }
+    }
+
+    /// Replaces the pending expression with typed input and evaluates it.
+    /// A function definition is kept instead and has no value.
+    pub fn evaluate_input(&mut self, input: &str) -> Result<Option<Value>, EvalError> {
+        if self.enter_input(input)? {
+            return Ok(None);
+        }
+        self.evaluate().map(Some)
+    }
+
+    // Makes typed input the pending token stream, or keeps it as a user
+    // function if it defines one. Tells which it was.
+    fn enter_input(&mut self, input: &str) -> Result<bool, EvalError> {
+        let mut tokens = lexer::tokenize(input, self.base, self.digit_limit)?;
+        for token in &mut tokens {
+            if let Token::Number(n) = token {
+                if n.is_integer() {
+                    *n = Value::from(self.entry_value(self.fit_entry(n.to_integer())?));
+                } else if self.arithmetic == ArithmeticMode::Integer {
+                    return Err(EvalError::NotAnInteger);
                 }
-                _ => i += 1,
             }
         }
+        self.add_token(Token::ClearScreen);
+        if let Ok(Statement::Definition(name, parameters, body)) = parser::parse(&tokens) {
+            let text = input.trim().to_string();
+            self.user_functions.insert(name, UserFunction { parameters, text, body });
+            return Ok(true);
+        }
+        self.token_stream = tokens;
+        Ok(false)
+    }
 
-        if ops.is_empty() || nums.len() == 1 {
-            return nums[0];
+    /// Evaluates the pending token stream, storing the result in `ans` and,
+    /// for an assignment, in its variable as well. On success the stream is
+    /// replaced by the result so the keypad can carry on from it; on failure
+    /// it is left untouched so it can be corrected.
+    pub fn evaluate(&mut self) -> Result<Value, EvalError> {
+        let scope = Scope::default();
+        let result = match parser::parse(&self.token_stream)? {
+            Statement::Expression(expr) => self.evaluate_expr(&expr, &scope)?,
+            Statement::Assignment(name, expr) => {
+                let value = self.evaluate_expr(&expr, &scope)?;
+                self.variables.insert(name, value.clone());
+                value
+            }
+            // Definitions are typed, never built on the keypad.
+            Statement::Definition(..) => return Err(EvalError::UnexpectedToken),
+        };
+        self.variables.insert(ANS.to_string(), result.clone());
+        self.token_stream.clear();
+        self.token_stream.push(Token::Number(result.clone()));
+        Ok(result)
+    }
+}
+
+// Operators. Integer arithmetic is exact and the result is then fitted to the
+// word; bitwise operators work on the word's bit pattern and can never
+// overflow. Rational arithmetic has no word and only answers to the digit
+// limit.
+impl Calculator {
+    fn apply_operator(&self, op: Operator, lhs: Value, rhs: Value) -> Result<Value, EvalError> {
+        match self.arithmetic {
+            ArithmeticMode::Integer => self
+                .apply_integer(op, lhs.to_integer(), rhs.to_integer())
+                .map(Value::from),
+            ArithmeticMode::Rational => self.apply_rational(op, lhs, rhs),
+            ArithmeticMode::Float => self.apply_float(op, lhs, rhs),
+            ArithmeticMode::Decimal => self.apply_decimal(op, lhs, rhs),
         }
+    }
 
-        let mut result = nums[0];
-        for (i, op) in ops.iter().enumerate() {
-            let next_num = nums[i + 1];
-            match op {
-                Operator::Addition => result += next_num,
-                Operator::Subtraction => result -= next_num,
-                _ => {}
+    fn apply_unary(&self, op: UnaryOperator, operand: Value) -> Result<Value, EvalError> {
+        match (op, self.arithmetic) {
+            (UnaryOperator::Negation, ArithmeticMode::Integer) => {
+                self.fit(-operand.to_integer()).map(Value::from)
             }
+            (UnaryOperator::Negation, _) => Ok(-operand),
+            (UnaryOperator::Not, _) => {
+                let operand = Self::require_integer(operand)?;
+                Ok(Value::from(self.word_size.wrap(&!operand, self.is_signed())))
+            }
+        }
+    }
+
+    fn require_integer(value: Value) -> Result<Integer, EvalError> {
+        if value.is_integer() {
+            Ok(value.to_integer())
+        } else {
+            Err(EvalError::NotAnInteger)
         }
-        result
     }
 
-    fn evaluate_full_expression(&mut self) -> i64 {
-        while self.token_stream.len() > 1 {
-            let mut numbers = Vec::new();
-            let mut operators = Vec::new();
-            for token in self.token_stream.drain(..) {
-                match token {
-                    Token::Number(n) => numbers.push(n),
-                    Token::Operator(o) => operators.push(o),
-                    _ => {}
+    fn apply_rational(&self, op: Operator, lhs: Value, rhs: Value) -> Result<Value, EvalError> {
+        let result = match op {
+            Operator::Addition => lhs + rhs,
+            Operator::Subtraction => lhs - rhs,
+            Operator::Multiplication => {
+                if lhs.bits() + rhs.bits() > self.bit_limit() + 1 {
+                    return Err(EvalError::DigitLimit);
                 }
+                lhs * rhs
+            }
+            Operator::Division
+            | Operator::Remainder
+            | Operator::Modulo
+            | Operator::EuclideanDivision
+                if rhs.is_zero() =>
+            {
+                return Err(EvalError::DivisionByZero);
+            }
+            Operator::Division => lhs / rhs,
+            Operator::Remainder => lhs % rhs,
+            // The Euclidean remainder lies in 0..|rhs|, and the quotient is
+            // whatever makes lhs = rhs * quotient + remainder hold.
+            Operator::Modulo => Self::rem_euclid(lhs, rhs),
+            Operator::EuclideanDivision => {
+                let remainder = Self::rem_euclid(lhs.clone(), rhs.clone());
+                (lhs - remainder) / rhs
             }
-            let result = self.calculate_equation(&operators, &numbers);
-            self.token_stream.push(Token::Number(result));
+            Operator::Power => return self.rational_power(lhs, rhs),
+            _ => {
+                let lhs = Self::require_integer(lhs)?;
+                let rhs = Self::require_integer(rhs)?;
+                return self.apply_integer(op, lhs, rhs).map(Value::from);
+            }
+        };
+        self.check_digit_limit(result.bits())?;
+        Ok(result)
+    }
+
+    // Every step is rounded to a double; integer-only operators still work
+    // on doubles that hold whole numbers.
+    fn apply_float(&self, op: Operator, lhs: Value, rhs: Value) -> Result<Value, EvalError> {
+        let (x, y) = (lhs.to_f64(), rhs.to_f64());
+        let result = match op {
+            Operator::Addition => x + y,
+            Operator::Subtraction => x - y,
+            Operator::Multiplication => x * y,
+            Operator::Division
+            | Operator::Remainder
+            | Operator::Modulo
+            | Operator::EuclideanDivision
+                if y == 0.0 =>
+            {
+                return Err(EvalError::DivisionByZero);
+            }
+            Operator::Division => x / y,
+            Operator::Remainder => x % y,
+            Operator::Modulo => x.rem_euclid(y),
+            Operator::EuclideanDivision => x.div_euclid(y),
+            Operator::Power => {
+                if x == 0.0 && y < 0.0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+                x.powf(y)
+            }
+            _ => {
+                let lhs = Self::require_integer(lhs)?;
+                let rhs = Self::require_integer(rhs)?;
+                return float_value(self.apply_integer(op, lhs, rhs)?.to_f64().unwrap_or(f64::NAN));
+            }
+        };
+        float_value(result)
+    }
+
+    // Exact arithmetic, rounded to the precision after every step.
+    fn apply_decimal(&self, op: Operator, lhs: Value, rhs: Value) -> Result<Value, EvalError> {
+        let result = match op {
+            Operator::Power => self.decimal_power(lhs, rhs)?,
+            _ => self.apply_rational(op, lhs, rhs)?,
+        };
+        Ok(self.round_decimal(&result))
+    }
+
+    fn round_decimal(&self, value: &Value) -> Value {
+        value.round_significant(self.precision, self.rounding)
+    }
+
+    // Square-and-multiply, rounding to a few guard digits along the way so
+    // that the exact power never has to be built.
+    fn decimal_power(&self, base: Value, exponent: Value) -> Result<Value, EvalError> {
+        let exponent = Self::require_integer(exponent)?;
+        if exponent.is_negative() && base.is_zero() {
+            return Err(EvalError::DivisionByZero);
+        }
+
+        let guard_digits = self.precision + 10;
+        let round = |value: Value| value.round_significant(guard_digits, RoundingMode::HalfEven);
+        let mut result = Value::from(1);
+        let mut square = base;
+        let mut remaining = exponent.abs();
+        while !remaining.is_zero() {
+            if remaining.bit(0) {
+                result = round(result * square.clone());
+            }
+            remaining >>= 1;
+            if !remaining.is_zero() {
+                square = round(square.clone() * square);
+            }
+            self.check_digit_limit(result.bits().max(square.bits()))?;
         }
-        if let Some(Token::Number(result)) = self.token_stream.first() {
-            *result
+        Ok(if exponent.is_negative() { result.recip() } else { result })
+    }
+
+    fn rem_euclid(lhs: Value, rhs: Value) -> Value {
+        let remainder = lhs % rhs.clone();
+        if remainder.is_negative() {
+            remainder + rhs.abs()
         } else {
-            0
+            remainder
         }
     }
 
-    pub fn evaluate(&mut self) -> i64 {
-        if !self
-            .token_stream
-            .iter()
-            .any(|t| matches!(t, Token::LeftParenthesis))
+    // Exponents have to be whole; a negative one takes the reciprocal.
+    fn rational_power(&self, base: Value, exponent: Value) -> Result<Value, EvalError> {
+        let exponent = Self::require_integer(exponent)?;
+        let (base, exponent) = if exponent.is_negative() {
+            if base.is_zero() {
+                return Err(EvalError::DivisionByZero);
+            }
+            (base.recip(), -exponent)
+        } else {
+            (base, exponent)
+        };
+
+        if base.is_integer()
+            && let Some(result) = Self::trivial_power(base.numer(), &exponent)
         {
-            return self.evaluate_full_expression();
+            return Ok(Value::from(result));
+        }
+        let min_bits = exponent
+            .to_u64()
+            .and_then(|e| e.checked_mul(base.bits() - 1));
+        match min_bits {
+            Some(bits) if bits <= self.bit_limit() => {
+                let result = base.pow(exponent.to_u32().ok_or(EvalError::DigitLimit)?);
+                self.check_digit_limit(result.bits())?;
+                Ok(result)
+            }
+            _ => Err(EvalError::DigitLimit),
         }
+    }
 
-        while self
-            .token_stream
-            .iter()
-            .any(|t| matches!(t, Token::LeftParenthesis))
-        {
-            let mut stack = Vec::new();
-            let mut start_idx = None;
-            let mut end_idx = None;
-
-            for (i, token) in self.token_stream.iter().enumerate() {
-                match token {
-                    Token::LeftParenthesis => stack.push(i),
-                    Token::RightParenthesis => {
-                        if let Some(idx) = stack.pop() {
-                            start_idx = Some(idx);
-                            end_idx = Some(i);
-                            break;
-                        }
+    fn apply_integer(&self, op: Operator, lhs: Integer, rhs: Integer) -> Result<Integer, EvalError> {
+        let result = match op {
+            Operator::Addition => lhs + rhs,
+            Operator::Subtraction => lhs - rhs,
+            Operator::Multiplication => {
+                // The product needs at least this many bits minus one.
+                if self.word_size == WordSize::Unbounded
+                    && lhs.bits() + rhs.bits() > self.bit_limit() + 1
+                {
+                    return Err(EvalError::DigitLimit);
+                }
+                lhs * rhs
+            }
+            Operator::Division
+            | Operator::Remainder
+            | Operator::Modulo
+            | Operator::EuclideanDivision
+                if rhs.is_zero() =>
+            {
+                return Err(EvalError::DivisionByZero);
+            }
+            Operator::Division => lhs / rhs,
+            Operator::Remainder => lhs % rhs,
+            Operator::Modulo => lhs.rem_euclid(&rhs),
+            Operator::EuclideanDivision => lhs.div_euclid(&rhs),
+            Operator::Power => return self.power(lhs, rhs),
+            Operator::And => return Ok(self.word_size.wrap(&(lhs & rhs), self.is_signed())),
+            Operator::Or => return Ok(self.word_size.wrap(&(lhs | rhs), self.is_signed())),
+            Operator::Xor => return Ok(self.word_size.wrap(&(lhs ^ rhs), self.is_signed())),
+            Operator::Nand => return Ok(self.word_size.wrap(&!(lhs & rhs), self.is_signed())),
+            Operator::Nor => return Ok(self.word_size.wrap(&!(lhs | rhs), self.is_signed())),
+            Operator::ShiftLeft
+            | Operator::LogicalShiftRight
+            | Operator::ArithmeticShiftRight
+            | Operator::RotateLeft
+            | Operator::RotateRight => return self.shift(op, lhs, rhs),
+        };
+        self.fit(result)
+    }
+
+    // Powers of 0, 1 and -1, and zeroth powers, whatever the exponent's size.
+    fn trivial_power(base: &Integer, exponent: &Integer) -> Option<Integer> {
+        if exponent.is_zero() {
+            Some(Integer::one())
+        } else if base.is_zero() || base.is_one() {
+            Some(base.clone())
+        } else if *base == -Integer::one() {
+            Some(if exponent.bit(0) { base.clone() } else { Integer::one() })
+        } else {
+            None
+        }
+    }
+
+    // |base| ^ exponent needs more than (bits - 1) * exponent bits, which
+    // settles whether a result fits before anything huge is computed. Results
+    // too big for a word are still cheap to wrap with a modular power.
+    fn power(&self, base: Integer, exponent: Integer) -> Result<Integer, EvalError> {
+        if exponent.is_negative() {
+            return Err(EvalError::NegativeExponent);
+        }
+        if let Some(result) = Self::trivial_power(&base, &exponent) {
+            return Ok(result);
+        }
+
+        let min_bits = exponent
+            .to_u64()
+            .and_then(|e| e.checked_mul(base.bits() - 1));
+        let Some(mask) = self.word_size.mask() else {
+            return match min_bits {
+                Some(bits) if bits <= self.bit_limit() => {
+                    self.fit(base.pow(exponent.to_u32().ok_or(EvalError::DigitLimit)?))
+                }
+                _ => Err(EvalError::DigitLimit),
+            };
+        };
+
+        let word_bits = self.word_size.bits().unwrap_or_default() as u64;
+        match min_bits {
+            Some(bits) if bits < word_bits => {
+                self.fit(base.pow(exponent.to_u32().unwrap_or_default()))
+            }
+            _ => match self.overflow_policy {
+                OverflowPolicy::Checked => Err(EvalError::Overflow),
+                OverflowPolicy::Wrapping => {
+                    let pattern = base.modpow(&exponent, &(mask + 1));
+                    Ok(self.word_size.wrap(&pattern, self.is_signed()))
+                }
+                OverflowPolicy::Saturating => {
+                    let (min, max) = self.word_size.range(self.is_signed()).unwrap_or_default();
+                    let negative = base.is_negative() && exponent.bit(0);
+                    Ok(if negative { min } else { max })
+                }
+            },
+        }
+    }
+
+    // Shifts and rotates work on the word's bit pattern, so an arithmetic
+    // shift copies the top bit even in an unsigned word. Rotations are
+    // periodic and accept any amount; shift amounts outside 0..bits are an
+    // error, taken modulo the width, or clamped to it depending on the policy.
+    // Without a word, shifts multiply or floor-divide by powers of two and
+    // there is nothing to rotate around.
+    fn shift(&self, op: Operator, value: Integer, amount: Integer) -> Result<Integer, EvalError> {
+        let Some(bits) = self.word_size.bits() else {
+            let amount = amount.to_u64().ok_or(EvalError::ShiftOutOfRange)?;
+            return match op {
+                Operator::ShiftLeft => {
+                    if value.bits().saturating_add(amount) > self.bit_limit() {
+                        return Err(EvalError::DigitLimit);
                     }
-                    _ => {}
+                    Ok(value << amount)
                 }
+                Operator::LogicalShiftRight | Operator::ArithmeticShiftRight => Ok(value >> amount),
+                _ => Err(EvalError::RotateUnbounded),
+            };
+        };
+
+        let width = Integer::from(bits);
+        let rotate = matches!(op, Operator::RotateLeft | Operator::RotateRight);
+        let amount = if rotate || (!amount.is_negative() && amount < width) {
+            amount.rem_euclid(&width)
+        } else {
+            match self.overflow_policy {
+                OverflowPolicy::Checked => return Err(EvalError::ShiftOutOfRange),
+                OverflowPolicy::Wrapping => amount.rem_euclid(&width),
+                OverflowPolicy::Saturating => amount.clamp(Integer::zero(), width),
             }
+        };
+        let amount = amount.to_u32().unwrap_or(bits);
+
+        let pattern = self.word_size.wrap(&value, false);
+        let result = match op {
+            Operator::ShiftLeft => pattern << amount,
+            Operator::LogicalShiftRight => pattern >> amount,
+            Operator::ArithmeticShiftRight => self.word_size.wrap(&value, true) >> amount,
+            Operator::RotateLeft => (&pattern << amount) | (&pattern >> (bits - amount)),
+            Operator::RotateRight => (&pattern >> amount) | (&pattern << (bits - amount)),
+            _ => unreachable!("not a shift operator"),
+        };
+        Ok(self.word_size.wrap(&result, self.is_signed()))
+    }
+}
+
+// Doubles come back into the exact world as the fraction they stand for.
+// Infinity is an overflow; NaN only comes from things like a fractional power
+// of a negative number.
+fn float_value(x: f64) -> Result<Value, EvalError> {
+    if x.is_nan() {
+        return Err(EvalError::Undefined);
+    }
+    Value::from_f64(x).ok_or(EvalError::Overflow)
+}
 
-            let start_idx = start_idx.expect("Mismatched parentheses");
-            let end_idx = end_idx.expect("Mismatched parentheses");
+/// Writes a double with 15 significant digits, which is all a double
+/// reliably carries, so `0.1 + 0.2` shows as `0.3`. Very large and very
+/// small magnitudes switch to scientific notation.
+fn format_float(x: f64) -> String {
+    let scientific = format!("{:.14e}", x);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or_default();
+    if !(-6..15).contains(&exponent) {
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        return format!("{}e{}", mantissa, exponent);
+    }
+
+    let positional = format!("{:.*}", (14 - exponent).max(0) as usize, x);
+    if positional.contains('.') {
+        positional.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        positional
+    }
+}
+
+// Functions. Rounding, absolute value and the extremes are exact in every
+// arithmetic; the rest are approximated to the precision, or on doubles in
+// floating-point arithmetic.
+impl Calculator {
+    fn call(&self, function: Function, arguments: Vec<Value>) -> Result<Value, EvalError> {
+        if !function.arity().contains(&arguments.len()) {
+            return Err(EvalError::ArgumentCount(function.name().to_string()));
+        }
+        let mut arguments = arguments.into_iter();
+        let x = arguments.next().unwrap_or_default();
 
-            let mut numbers = Vec::new();
-            let mut operators = Vec::new();
-            for token in self.token_stream.drain(start_idx..=end_idx) {
-                match token {
-                    Token::Number(n) => numbers.push(n),
-                    Token::Operator(o) => operators.push(o),
-                    _ => {}
+        let result = match function {
+            Function::Abs => x.abs(),
+            // A real number is its own conjugate and real part, and lies at
+            // a half turn from the positive axis when negative.
+            Function::Conj | Function::Re => x,
+            Function::Im => Value::from(0),
+            Function::Arg if x.is_negative() => {
+                return self.convert_angle(Value::from(180), AngleUnit::Degrees);
+            }
+            Function::Arg => Value::from(0),
+            Function::Floor => Value::from(x.round(RoundingMode::Floor)),
+            Function::Ceil => Value::from(x.round(RoundingMode::Ceiling)),
+            Function::Round => match arguments.next() {
+                // Rounding scales by 10^places, which has as many digits.
+                Some(places) => {
+                    let places = Self::require_integer(places)?
+                        .to_i64()
+                        .filter(|places| places.unsigned_abs() <= self.digit_limit)
+                        .ok_or(EvalError::DigitLimit)?;
+                    x.checked_round_places(places, self.rounding)
+                        .ok_or(EvalError::DigitLimit)?
                 }
+                None => Value::from(x.round(self.rounding)),
+            },
+            Function::Min => arguments.fold(x, |min, y| min.min(y)),
+            Function::Max => arguments.fold(x, |max, y| max.max(y)),
+            _ => return self.approximate(function, x, self.angle_unit),
+        };
+        match self.arithmetic {
+            ArithmeticMode::Integer => self.fit(result.to_integer()).map(Value::from),
+            ArithmeticMode::Decimal => Ok(self.round_decimal(&result)),
+            _ => Ok(result),
+        }
+    }
+
+    fn approximate(&self, function: Function, x: Value, unit: AngleUnit) -> Result<Value, EvalError> {
+        match self.arithmetic {
+            ArithmeticMode::Float => float_value(functions::approximate_f64(function, x.to_f64(), unit)),
+            // Whole roots of whole numbers, rounded down; anything else is
+            // rounded to the precision first so that log10(1000) is 3 and
+            // not 2.999...
+            ArithmeticMode::Integer => {
+                let x = x.to_integer();
+                let result = match function {
+                    Function::Sqrt if x.is_negative() => return Err(EvalError::Undefined),
+                    Function::Sqrt => x.sqrt(),
+                    Function::Cbrt => x.cbrt(),
+                    _ => functions::approximate(
+                        function,
+                        &Value::from(x),
+                        unit,
+                        self.precision,
+                        self.digit_limit,
+                    )?
+                        .round_significant(self.precision, RoundingMode::HalfEven)
+                        .to_integer(),
+                };
+                self.fit(result).map(Value::from)
+            }
+            ArithmeticMode::Rational | ArithmeticMode::Decimal => {
+                let result = functions::approximate(
+                    function,
+                    &x,
+                    unit,
+                    self.precision,
+                    self.digit_limit,
+                )?;
+                self.check_digit_limit(result.bits())?;
+                Ok(result.round_significant(self.precision, self.rounding))
+            }
+        }
+    }
+
+    /// A constant in this calculator's arithmetic: truncated to a whole
+    /// number, to a double, or rounded to the precision unless it is exact.
+    fn constant(&self, constant: &Constant) -> Result<Value, EvalError> {
+        let value = constant.value(self.precision);
+        match self.arithmetic {
+            ArithmeticMode::Integer => self.fit(value.to_integer()).map(Value::from),
+            ArithmeticMode::Float => float_value(value.to_f64()),
+            ArithmeticMode::Rational if constant.is_exact() => Ok(value),
+            _ => Ok(value.round_significant(self.precision, self.rounding)),
+        }
+    }
+
+    // A value with a unit suffix, brought into the calculator's angle unit.
+    fn convert_angle(&self, x: Value, from: AngleUnit) -> Result<Value, EvalError> {
+        let to = self.angle_unit;
+        if self.arithmetic == ArithmeticMode::Float {
+            return float_value(functions::convert_angle_f64(x.to_f64(), from, to));
+        }
+        let result = functions::convert_angle(&x, from, to, self.precision);
+        self.check_digit_limit(result.bits())?;
+        // Only radians bring pi in; the other conversions stay exact.
+        let exact = from == to || (from.right_angle().is_some() && to.right_angle().is_some());
+        match self.arithmetic {
+            ArithmeticMode::Integer => self
+                .fit(result.round_significant(self.precision, RoundingMode::HalfEven).to_integer())
+                .map(Value::from),
+            ArithmeticMode::Rational if exact => Ok(result),
+            _ => Ok(result.round_significant(self.precision, self.rounding)),
+        }
+    }
+}
+
+// Complex mode. Everything is on doubles, as in floating-point arithmetic,
+// with `i` as the imaginary unit. Real arguments that stay real go through
+// the ordinary functions, so `sin(180)` in degrees is still exactly zero;
+// operators and functions with no complex meaning only take real operands.
+impl Calculator {
+    /// Evaluates the pending token stream as complex numbers, storing the
+    /// result in complex mode's own `ans` and variables. On success the
+    /// stream is replaced by the result, or by `ans` when it is not real.
+    pub fn evaluate_complex(&mut self) -> Result<Complex, EvalError> {
+        let scope = Scope::default();
+        let result = match parser::parse(&self.token_stream)? {
+            Statement::Expression(expr) => self.evaluate_complex_expr(&expr, &scope)?,
+            Statement::Assignment(name, _) if name == IMAGINARY_UNIT => {
+                return Err(EvalError::ReadOnly(name));
             }
+            Statement::Assignment(name, expr) => {
+                let value = self.evaluate_complex_expr(&expr, &scope)?;
+                self.complex_variables.insert(name, value);
+                value
+            }
+            Statement::Definition(..) => return Err(EvalError::UnexpectedToken),
+        };
+        self.complex_variables.insert(ANS.to_string(), result);
+        self.token_stream.clear();
+        self.token_stream.push(if result.is_real() {
+            Token::Number(float_value(result.re)?)
+        } else {
+            Token::Identifier(ANS.to_string())
+        });
+        Ok(result)
+    }
+
+    /// Complex counterpart of `evaluate_input`.
+    pub fn evaluate_complex_input(&mut self, input: &str) -> Result<Option<Complex>, EvalError> {
+        if self.enter_input(input)? {
+            return Ok(None);
+        }
+        self.evaluate_complex().map(Some)
+    }
+
+    fn evaluate_complex_expr(&self, expr: &Expr, scope: &Scope<Complex>) -> Result<Complex, EvalError> {
+        let result = match expr {
+            Expr::Number(n) => Complex::from_real(n.to_f64()),
+            // Parameters come first, then the imaginary unit, then values
+            // kept in complex mode and lastly the real variables.
+            Expr::Variable(name) => match scope.arguments.get(name.as_str()) {
+                Some(value) => *value,
+                None if name == IMAGINARY_UNIT => Complex::I,
+                None => match (self.complex_variables.get(name), self.variables.get(name)) {
+                    (Some(value), _) => *value,
+                    (None, Some(value)) => Complex::from_real(value.to_f64()),
+                    (None, None) => return Err(EvalError::UnknownName(name.clone())),
+                },
+            },
+            Expr::Unary(UnaryOperator::Negation, operand) => -self.evaluate_complex_expr(operand, scope)?,
+            Expr::Unary(op, operand) => {
+                let operand = self.evaluate_complex_expr(operand, scope)?;
+                complex_value(self.apply_unary(*op, real_value(operand)?)?)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.evaluate_complex_expr(lhs, scope)?;
+                if let Expr::Percent(percent) = rhs.as_ref() {
+                    let percent = self.evaluate_complex_expr(percent, scope)?;
+                    let result = self.apply_percent(*op, real_value(lhs)?, real_value(percent)?)?;
+                    return Ok(complex_value(result));
+                }
+                let rhs = self.evaluate_complex_expr(rhs, scope)?;
+                self.apply_complex(*op, lhs, rhs)?
+            }
+            Expr::Percent(operand) => self.evaluate_complex_expr(operand, scope)? / Complex::from_real(100.0),
+            Expr::Call(function, arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.evaluate_complex_expr(argument, scope))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call_complex(*function, arguments)?
+            }
+            Expr::UserCall(name, arguments) => {
+                let function = self.user_function(name, arguments.len(), scope.depth)?;
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.evaluate_complex_expr(argument, scope))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let inner = Scope {
+                    arguments: function.parameters.iter().map(String::as_str).zip(arguments).collect(),
+                    depth: scope.depth + 1,
+                };
+                self.evaluate_complex_expr(&function.body, &inner)?
+            }
+            Expr::Constant(constant) => complex_value(self.constant(constant)?),
+            // Converting an angle only scales it, so it carries over to
+            // complex numbers unchanged.
+            Expr::Angle(unit, operand) => {
+                let factor = functions::convert_angle_f64(1.0, *unit, self.angle_unit);
+                self.evaluate_complex_expr(operand, scope)?.scale(factor)
+            }
+        };
+        finite(result)
+    }
+
+    fn apply_complex(&self, op: Operator, lhs: Complex, rhs: Complex) -> Result<Complex, EvalError> {
+        match op {
+            Operator::Addition => Ok(lhs + rhs),
+            Operator::Subtraction => Ok(lhs - rhs),
+            Operator::Multiplication => Ok(lhs * rhs),
+            Operator::Division if rhs.is_zero() => Err(EvalError::DivisionByZero),
+            Operator::Division => Ok(lhs / rhs),
+            // Zero to a power is only defined when the power has a positive
+            // real part, or is zero itself.
+            Operator::Power if lhs.is_zero() => match rhs {
+                _ if rhs.is_zero() => Ok(Complex::from_real(1.0)),
+                _ if rhs.re > 0.0 => Ok(Complex::default()),
+                _ if rhs.is_real() => Err(EvalError::DivisionByZero),
+                _ => Err(EvalError::Undefined),
+            },
+            Operator::Power => Ok(lhs.pow(rhs)),
+            _ => Ok(complex_value(self.apply_operator(op, real_value(lhs)?, real_value(rhs)?)?)),
+        }
+    }
+
+    fn call_complex(&self, function: Function, arguments: Vec<Complex>) -> Result<Complex, EvalError> {
+        if !function.arity().contains(&arguments.len()) {
+            return Err(EvalError::ArgumentCount(function.name().to_string()));
+        }
+        let z = arguments[0];
+        // Roots and logarithms of negative numbers, and inverse sines and
+        // cosines beyond one, are where real arguments turn complex.
+        let leaves_reals = match function {
+            Function::Sqrt | Function::Ln | Function::Log10 | Function::Log2 => z.re < 0.0,
+            Function::Asin | Function::Acos => z.re.abs() > 1.0,
+            _ => false,
+        };
+        if arguments.iter().all(|argument| argument.is_real()) && !leaves_reals {
+            let arguments = arguments.into_iter().map(real_value).collect::<Result<_, _>>()?;
+            return Ok(complex_value(self.call(function, arguments)?));
+        }
+
+        let to_radians = functions::convert_angle_f64(1.0, self.angle_unit, AngleUnit::Radians);
+        let from_radians = |angle: Complex| angle.scale(1.0 / to_radians);
+        let result = match function {
+            Function::Sin => z.scale(to_radians).sin(),
+            Function::Cos => z.scale(to_radians).cos(),
+            Function::Tan => {
+                let cos = z.scale(to_radians).cos();
+                if cos.is_zero() {
+                    return Err(EvalError::Undefined);
+                }
+                z.scale(to_radians).sin() / cos
+            }
+            Function::Asin => from_radians(z.asin()),
+            Function::Acos => from_radians(Complex::from_real(std::f64::consts::FRAC_PI_2) - z.asin()),
+            Function::Atan => from_radians(z.atan()),
+            Function::Exp => z.exp(),
+            Function::Ln => z.ln(),
+            Function::Log10 => z.ln().scale(1.0 / std::f64::consts::LN_10),
+            Function::Log2 => z.ln().scale(1.0 / std::f64::consts::LN_2),
+            Function::Sqrt => z.sqrt(),
+            Function::Cbrt => z.pow(Complex::from_real(1.0 / 3.0)),
+            Function::Abs => Complex::from_real(z.abs()),
+            Function::Arg => Complex::from_real(z.arg() / to_radians),
+            Function::Conj => z.conj(),
+            Function::Re => Complex::from_real(z.re),
+            Function::Im => Complex::from_real(z.im),
+            Function::Floor | Function::Ceil | Function::Round | Function::Min | Function::Max => {
+                return Err(EvalError::NotReal);
+            }
+        };
+        finite(result)
+    }
+
+    /// Writes a complex number as `a + bi`, or as its magnitude and its
+    /// angle in the calculator's angle unit, as in `2 ∠ 90deg`.
+    pub fn format_complex(&self, z: &Complex) -> String {
+        let z = clean(*z);
+        match self.complex_format {
+            ComplexFormat::Rectangular => {
+                let imaginary = |im: f64| match im {
+                    1.0 => IMAGINARY_UNIT.to_string(),
+                    _ => format!("{}{}", format_float(im), IMAGINARY_UNIT),
+                };
+                match (z.re, z.im) {
+                    (_, 0.0) => format_float(z.re),
+                    (0.0, -1.0) => format!("-{}", IMAGINARY_UNIT),
+                    (0.0, im) => imaginary(im),
+                    (re, im) if im < 0.0 => format!("{} - {}", format_float(re), imaginary(-im)),
+                    (re, im) => format!("{} + {}", format_float(re), imaginary(im)),
+                }
+            }
+            ComplexFormat::Polar => {
+                let angle = functions::convert_angle_f64(z.arg(), AngleUnit::Radians, self.angle_unit);
+                format!("{} ∠ {}{}", format_float(z.abs()), format_float(angle), self.angle_unit.suffix())
+            }
+        }
+    }
+}
+
+fn complex_value(x: Value) -> Complex {
+    Complex::from_real(x.to_f64())
+}
+
+fn real_value(z: Complex) -> Result<Value, EvalError> {
+    if !z.is_real() {
+        return Err(EvalError::NotReal);
+    }
+    float_value(z.re)
+}
+
+// Like `float_value`, infinity is an overflow and NaN is undefined. Zeros
+// lose their sign, so that -1 lies on the same side of the branch cut
+// however it was reached and ln(-1) is always pi i.
+fn finite(z: Complex) -> Result<Complex, EvalError> {
+    if z.re.is_nan() || z.im.is_nan() {
+        return Err(EvalError::Undefined);
+    }
+    if !z.is_finite() {
+        return Err(EvalError::Overflow);
+    }
+    Ok(Complex::new(z.re + 0.0, z.im + 0.0))
+}
+
+// Rounding error leaves crumbs like the 1.2e-16i in exp(i pi); a part that
+// small beside the other is taken to be zero for display.
+fn clean(z: Complex) -> Complex {
+    let crumb = z.abs() * 1e-15;
+    let part = |x: f64| if x.abs() < crumb { 0.0 } else { x };
+    Complex::new(part(z.re), part(z.im))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(calculator: &mut Calculator, input: &str) -> Result<Value, EvalError> {
+        calculator.evaluate_input(input).map(|result| result.expect("not a definition"))
+    }
+
+    fn integer(input: &str) -> Result<Value, EvalError> {
+        eval(&mut Calculator::new(), input)
+    }
+
+    fn exact(input: &str) -> Result<Value, EvalError> {
+        eval(&mut Calculator::new().with_arithmetic(ArithmeticMode::Rational), input)
+    }
+
+    fn word(word_size: WordSize, policy: OverflowPolicy) -> Calculator {
+        let mut calculator = Calculator::new().with_word_size(word_size);
+        calculator.set_overflow_policy(policy);
+        calculator
+    }
+
+    fn v(n: i64) -> Value {
+        Value::from(n)
+    }
+
+    #[test]
+    fn evaluates_with_precedence_and_associativity() {
+        assert_eq!(integer("2 + 3 * 4"), Ok(v(14)));
+        assert_eq!(integer("10 - 3 - 2"), Ok(v(5)));
+        assert_eq!(integer("2 ^ 3 ^ 2"), Ok(v(512)));
+        assert_eq!(integer("-2 ^ 2"), Ok(v(-4)));
+        assert_eq!(integer("(2 + 3) * 4"), Ok(v(20)));
+        assert_eq!(integer("1 | 6 & 3"), Ok(v(3)));
+        assert_eq!(integer("6 xor 3 nand 7"), Ok(v(-6)));
+        assert_eq!(integer("7 / 2"), Ok(v(3)));
+        assert_eq!(exact("7 / 2"), Ok(Value::new(7.into(), 2.into())));
+        assert_eq!(integer("2 ^ 100"), Ok(Value::from(Integer::one() << 100)));
+    }
+
+    #[test]
+    fn divides_with_remainders() {
+        assert_eq!(integer("7 % 3"), Ok(v(1)));
+        assert_eq!(integer("-7 % 3"), Ok(v(-1)));
+        assert_eq!(integer("-7 mod 3"), Ok(v(2)));
+        assert_eq!(integer("7 mod -3"), Ok(v(1)));
+        assert_eq!(integer("-7 div 2"), Ok(v(-4)));
+        assert_eq!(integer("-7 / 2"), Ok(v(-3)));
+    }
+
+    #[test]
+    fn shifts_and_rotates_the_word() {
+        let mut qword = Calculator::new().with_word_size(WordSize::Qword);
+        assert_eq!(eval(&mut qword, "1 << 63"), Ok(v(i64::MIN)));
+        assert_eq!(eval(&mut qword, "-16 >> 2"), Ok(v(-4)));
+        assert_eq!(eval(&mut qword, "-16 >>> 60"), Ok(v(15)));
+        assert_eq!(eval(&mut qword, "1 ror 1"), Ok(v(i64::MIN)));
+        // Rotations accept any amount.
+        assert_eq!(eval(&mut qword, "1 rol 65"), Ok(v(2)));
+        assert_eq!(eval(&mut qword, "1 rol -1"), Ok(v(i64::MIN)));
+        // Without a word, shifts just scale.
+        assert_eq!(integer("1 << 100"), Ok(Value::from(Integer::one() << 100)));
+        assert_eq!(integer("-16 >> 2"), Ok(v(-4)));
+    }
+
+    #[test]
+    fn reads_bit_patterns_in_other_bases() {
+        let mut hex = Calculator::new().with_base(16).with_word_size(WordSize::Byte);
+        assert_eq!(eval(&mut hex, "FF"), Ok(v(-1)));
+        assert_eq!(hex.format_number(&v(-1)), "FF");
+        hex.set_signed(false);
+        assert_eq!(eval(&mut hex, "FF"), Ok(v(255)));
+        assert_eq!(eval(&mut hex, "100"), Err(EvalError::Overflow));
+        assert_eq!(eval(&mut hex, "80 >>> 4"), Ok(v(8)));
+        // An arithmetic shift copies the top bit whether or not it is a sign.
+        assert_eq!(eval(&mut hex, "80 >> 4"), Ok(v(0xF8)));
+        assert_eq!(eval(&mut hex, "81 rol 1"), Ok(v(3)));
+        // Kept values are read again in the new word.
+        assert_eq!(eval(&mut hex, "x = FF"), Ok(v(255)));
+        hex.set_signed(true);
+        assert_eq!(hex.variables().get("ans"), Some(&v(-1)));
+        assert_eq!(eval(&mut hex, "x"), Ok(v(-1)));
+
+        // In base 36 a number spelled like a keyword takes a leading zero.
+        let mut radix = Calculator::new().with_base(36);
+        assert_eq!(eval(&mut radix, "0AND"), Ok(v(13_801)));
+        assert_eq!(eval(&mut radix, "1 AND 3"), Ok(v(1)));
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let mut float = Calculator::new().with_arithmetic(ArithmeticMode::Float);
+        for input in ["1/10000000", "1/3", "2^70", "-1/2^30", "0.1 + 0.2", "1e300 * 10", "-123456.789"] {
+            let result = eval(&mut float, input).unwrap();
+            let shown = float.format_number(&result);
+            let again = eval(&mut float, &shown).unwrap();
+            assert_eq!(float.format_number(&again), shown, "{}", input);
+        }
+
+        let mut decimal = Calculator::new().with_arithmetic(ArithmeticMode::Decimal);
+        for input in ["1/10000000", "1/7", "-2^200", "10^-60 / 3", "1.5e3", "pi"] {
+            let result = eval(&mut decimal, input).unwrap();
+            let shown = decimal.format_number(&result);
+            assert_eq!(eval(&mut decimal, &shown), Ok(result), "{} shown as {}", input, shown);
+        }
+
+        let mut exact = Calculator::new().with_arithmetic(ArithmeticMode::Rational);
+        exact.set_rational_format(RationalFormat::Decimal);
+        let result = eval(&mut exact, "1/6").unwrap();
+        let shown = exact.format_number(&result);
+        assert_eq!(shown, "0.1[6]");
+        assert_eq!(eval(&mut exact, &shown), Ok(result));
+
+        for base in [2, 8, 16, 36] {
+            let mut calculator = Calculator::new().with_base(base).with_word_size(WordSize::Dword);
+            let result = v(-123_456);
+            let shown = calculator.format_number(&result);
+            assert_eq!(eval(&mut calculator, &shown), Ok(result), "base {}", base);
+        }
+    }
+
+    #[test]
+    fn rounds_decimals_to_the_precision() {
+        let mut decimal = Calculator::new().with_arithmetic(ArithmeticMode::Decimal);
+        decimal.set_precision(5);
+        assert_eq!(eval(&mut decimal, "2/3"), Ok(Value::new(66667.into(), 100000.into())));
+        decimal.set_rounding(RoundingMode::Floor);
+        assert_eq!(eval(&mut decimal, "2/3"), Ok(Value::new(66666.into(), 100000.into())));
+        assert_eq!(eval(&mut decimal, "123456"), Ok(v(123450)));
+    }
+
+    #[test]
+    fn calls_functions() {
+        assert_eq!(integer("sqrt(17)"), Ok(v(4)));
+        assert_eq!(integer("log10(1000)"), Ok(v(3)));
+        assert_eq!(integer("max(1, 5, 3) - min(4, 2)"), Ok(v(3)));
+        assert_eq!(exact("abs(-7/2) + floor(-1/2)"), Ok(Value::new(5.into(), 2.into())));
+
+        let mut decimal = Calculator::new().with_arithmetic(ArithmeticMode::Decimal);
+        decimal.set_precision(10);
+        assert_eq!(eval(&mut decimal, "exp(1)"), Ok(Value::new(2_718_281_828i64.into(), 1_000_000_000.into())));
+        assert_eq!(eval(&mut decimal, "tau"), Ok(Value::new(6_283_185_307i64.into(), 1_000_000_000.into())));
+        assert_eq!(eval(&mut decimal, "ln(10^9000)"), Ok(Value::new(2_072_326_584i64.into(), 100_000.into())));
+        assert_eq!(eval(&mut decimal, "sin(10^2000)"), Err(EvalError::DigitLimit));
+        // Exact constants stay exact.
+        assert_eq!(exact("c / 2"), Ok(v(149_896_229)));
+    }
+
+    #[test]
+    fn works_in_the_angle_unit() {
+        let mut decimal = Calculator::new().with_arithmetic(ArithmeticMode::Decimal);
+        decimal.set_angle_unit(AngleUnit::Degrees);
+        assert_eq!(eval(&mut decimal, "sin(30)"), Ok(Value::new(1.into(), 2.into())));
+        assert_eq!(eval(&mut decimal, "cos(90)"), Ok(v(0)));
+        assert_eq!(eval(&mut decimal, "asin(1)"), Ok(v(90)));
+        assert_eq!(eval(&mut decimal, "100grad"), Ok(v(90)));
+        assert_eq!(eval(&mut decimal, "tan(90)"), Err(EvalError::Undefined));
+        decimal.set_angle_unit(AngleUnit::Radians);
+        assert_eq!(eval(&mut decimal, "sin(180deg)"), Ok(v(0)));
+        assert_eq!(eval(&mut decimal, "tan(90deg)"), Err(EvalError::Undefined));
+    }
+
+    #[test]
+    fn keeps_variables_and_functions() {
+        let mut calculator = Calculator::new();
+        assert_eq!(eval(&mut calculator, "x = 6"), Ok(v(6)));
+        assert_eq!(eval(&mut calculator, "x * 7"), Ok(v(42)));
+        assert_eq!(eval(&mut calculator, "ans + 1"), Ok(v(43)));
+        assert_eq!(calculator.variables().get("x"), Some(&v(6)));
+        assert_eq!(calculator.evaluate_input("f(a, b) = a * b + x"), Ok(None));
+        assert_eq!(eval(&mut calculator, "f(2, 3)"), Ok(v(12)));
+        // Parameters hide variables.
+        assert_eq!(calculator.evaluate_input("g(x) = x"), Ok(None));
+        assert_eq!(eval(&mut calculator, "g(1)"), Ok(v(1)));
+    }
+
+    #[test]
+    fn tells_percent_from_remainder() {
+        assert_eq!(integer("7 % 3"), Ok(v(1)));
+        assert_eq!(integer("7 % -3"), Ok(v(1)));
+        assert_eq!(integer("7 % +3"), Ok(v(1)));
+        assert_eq!(integer("-7 % 3"), Ok(v(-1)));
+        assert_eq!(integer("-7 mod 3"), Ok(v(2)));
+        assert_eq!(integer("200 + 10%"), Ok(v(220)));
+        assert_eq!(integer("200 - 10%"), Ok(v(180)));
+        assert_eq!(integer("200 * 10%"), Ok(v(20)));
+        assert_eq!(integer("200 / 10%"), Ok(v(2000)));
+        assert_eq!(exact("50%"), Ok(Value::new(1.into(), 2.into())));
+
+        let mut calculator = Calculator::new();
+        for token in [
+            Token::Digit(2),
+            Token::Digit(0),
+            Token::Digit(0),
+            Token::Operator(Operator::Addition),
+            Token::Digit(1),
+            Token::Digit(0),
+            Token::Percent,
+            Token::Equals,
+        ] {
+            calculator.add_token(token);
+        }
+        assert_eq!(calculator.evaluate(), Ok(v(220)));
+    }
+
+    #[test]
+    fn works_in_the_complex_plane() {
+        let mut complex = Calculator::new().with_arithmetic(ArithmeticMode::Float);
+        let mut show = |input: &str| {
+            let z = complex.evaluate_complex_input(input)?.expect("not a definition");
+            Ok(complex.format_complex(&z))
+        };
+        assert_eq!(show("sqrt(-4)"), Ok("2i".to_string()));
+        assert_eq!(show("(1 + i)(1 - i)"), Ok("2".to_string()));
+        assert_eq!(show("exp(pi i)"), Ok("-1".to_string()));
+        assert_eq!(show("ln(-1)"), Ok("3.14159265358979i".to_string()));
+        assert_eq!(show("floor(i)"), Err(EvalError::NotReal));
+
+        complex.set_angle_unit(AngleUnit::Degrees);
+        complex.set_complex_format(ComplexFormat::Polar);
+        let z = complex.evaluate_complex_input("2i").unwrap().unwrap();
+        assert_eq!(complex.format_complex(&z), "2 ∠ 90deg");
+    }
+
+    #[test]
+    fn shows_the_value_in_other_bases() {
+        let mut hex = Calculator::new().with_base(16).with_word_size(WordSize::Byte);
+        assert_eq!(hex.shown_value(), None);
+        hex.add_token(Token::Digit(15));
+        hex.add_token(Token::Digit(15));
+        assert_eq!(hex.shown_value(), Some(v(-1)));
+        hex.add_token(Token::Operator(Operator::Addition));
+        assert_eq!(hex.shown_value(), Some(v(-1)));
+        assert_eq!(hex.format_in_base(&Integer::from(-1), 2), "11111111");
+        assert_eq!(hex.format_in_base(&Integer::from(-1), 8), "377");
+        assert_eq!(hex.format_in_base(&Integer::from(-1), 10), "-1");
+    }
+
+    #[test]
+    fn carries_expressions_between_modes() {
+        let mut decimal = Calculator::new().with_arithmetic(ArithmeticMode::Decimal);
+        let mut binary = Calculator::new().with_base(2).with_word_size(WordSize::Byte);
+        eval(&mut decimal, "1.5 * 3").unwrap();
+        binary.set_expression(decimal.expression().unwrap()).unwrap();
+        assert_eq!(binary.get_display(), "100");
+
+        let mut complex = Calculator::new().with_arithmetic(ArithmeticMode::Float);
+        let mut binary = Calculator::new().with_base(2).with_word_size(WordSize::Qword);
+        eval(&mut binary, "111").unwrap();
+        complex.evaluate_complex_input("sqrt(-4)").unwrap();
+        assert_eq!(complex.expression().err(), Some(EvalError::NotReal));
+        complex.evaluate_complex_input("sqrt(4)").unwrap();
+        binary.set_expression(complex.expression().unwrap()).unwrap();
+        assert_eq!(binary.evaluate(), Ok(v(2)));
+
+        // Variables go as their values, not to be looked up again.
+        eval(&mut decimal, "x = 3").unwrap();
+        eval(&mut decimal, "1").unwrap();
+        decimal.enter_input("x + ans + 0.5").unwrap();
+        binary.set_expression(decimal.expression().unwrap()).unwrap();
+        assert_eq!(binary.get_display(), "11 + 1 + 0");
+
+        let mut hex = Calculator::new().with_base(16).with_word_size(WordSize::Qword);
+        eval(&mut hex, "5").unwrap();
+        eval(&mut decimal, "2^70").unwrap();
+        assert_eq!(hex.set_expression(decimal.expression().unwrap()), Err(EvalError::Overflow));
+        assert_eq!(hex.get_display(), "5");
+        hex.set_overflow_policy(OverflowPolicy::Saturating);
+        hex.set_expression(decimal.expression().unwrap()).unwrap();
+        assert_eq!(hex.get_display(), "7FFFFFFFFFFFFFFF");
+    }
+
+    #[test]
+    fn flips_bits_of_the_operand_being_entered() {
+        let mut binary = Calculator::new().with_base(2).with_word_size(WordSize::Byte);
+        for digit in [1, 0, 1] {
+            binary.add_token(Token::Digit(digit));
+        }
+        assert_eq!(binary.bit_operand(), Some(Integer::from(5)));
+        binary.toggle_bit(1);
+        assert_eq!(binary.get_display(), "111");
+
+        // After an operator the grid shows the operand still to come.
+        binary.add_token(Token::Operator(Operator::Addition));
+        assert_eq!(binary.bit_operand(), Some(Integer::zero()));
+        binary.toggle_bit(1);
+        assert_eq!(binary.get_display(), "111 + 10");
+
+        binary.add_token(Token::Equals);
+        assert_eq!(binary.evaluate(), Ok(v(9)));
+        binary.toggle_bit(7);
+        assert_eq!(binary.bit_operand(), Some(Integer::from(-119)));
+        assert_eq!(binary.get_display(), "10001001");
+
+        // A parenthesised value has no bits of its own to flip.
+        binary.enter_input("(101)").unwrap();
+        assert_eq!(binary.bit_operand(), None);
+        binary.toggle_bit(1);
+        assert_eq!(binary.get_display(), "( 101 )");
+    }
+
+    #[test]
+    fn limits_the_places_to_round_to() {
+        let mut decimal = Calculator::new().with_arithmetic(ArithmeticMode::Decimal);
+        assert_eq!(eval(&mut decimal, "round(1.25, 1)"), Ok(Value::new(6.into(), 5.into())));
+        assert_eq!(eval(&mut decimal, "round(1250, -2)"), Ok(v(1200)));
+        assert_eq!(eval(&mut decimal, "round(1.5, 100000000)"), Err(EvalError::DigitLimit));
+        assert_eq!(eval(&mut decimal, "round(1.5, -4294967295)"), Err(EvalError::DigitLimit));
+        assert_eq!(eval(&mut decimal, "round(1.25, 4294967297)"), Err(EvalError::DigitLimit));
+    }
+
+    #[test]
+    fn builds_expressions_from_the_keypad() {
+        let mut calculator = Calculator::new();
+        for token in [
+            Token::Digit(2),
+            Token::LeftParenthesis,
+            Token::Digit(3),
+            Token::Operator(Operator::Addition),
+            Token::Digit(4),
+            Token::RightParenthesis,
+            Token::Operator(Operator::Subtraction),
+            // A second operator replaces the first.
+            Token::Operator(Operator::Multiplication),
+            Token::Digit(5),
+            Token::Equals,
+        ] {
+            calculator.add_token(token);
+        }
+        assert_eq!(calculator.get_display(), "2 * ( 3 + 4 ) * 5");
+        assert_eq!(calculator.evaluate(), Ok(v(70)));
+        assert_eq!(calculator.get_display(), "70");
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(integer(""), Err(EvalError::EmptyExpression));
+        assert_eq!(integer("1 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(integer("2 ^ -1"), Err(EvalError::NegativeExponent));
+        assert_eq!(integer("5 mod 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(exact("0 ^ -1"), Err(EvalError::DivisionByZero));
+        assert_eq!(exact("2 ^ (1/2)"), Err(EvalError::NotAnInteger));
+        assert_eq!(exact("(1/2) & 1"), Err(EvalError::NotAnInteger));
+        assert_eq!(integer("1.5"), Err(EvalError::NotAnInteger));
+        assert_eq!(integer("1 rol 1"), Err(EvalError::RotateUnbounded));
+        assert_eq!(integer("y + 1"), Err(EvalError::UnknownName("y".to_string())));
+        assert_eq!(integer("max()"), Err(EvalError::ArgumentCount("max".to_string())));
+        assert_eq!(exact("sqrt(-1)"), Err(EvalError::Undefined));
+        assert_eq!(integer("pi = 3"), Err(EvalError::ReadOnly("pi".to_string())));
+        assert_eq!(integer("10 ^ 100000"), Err(EvalError::DigitLimit));
+
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.evaluate_input("f(x) = f(x)"), Ok(None));
+        assert_eq!(eval(&mut calculator, "f(1)"), Err(EvalError::RecursionLimit));
+        assert_eq!(eval(&mut calculator, "f(1, 2)"), Err(EvalError::ArgumentCount("f".to_string())));
+
+        // A failed evaluation leaves the input to be corrected.
+        let mut calculator = Calculator::new();
+        assert_eq!(eval(&mut calculator, "1 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(calculator.get_display(), "1 / 0");
+    }
+
+    #[test]
+    fn applies_the_overflow_policy() {
+        let mut checked = word(WordSize::Byte, OverflowPolicy::Checked);
+        let mut wrapping = word(WordSize::Byte, OverflowPolicy::Wrapping);
+        let mut saturating = word(WordSize::Byte, OverflowPolicy::Saturating);
+        assert_eq!(eval(&mut checked, "127 + 1"), Err(EvalError::Overflow));
+        assert_eq!(eval(&mut wrapping, "127 + 1"), Ok(v(-128)));
+        assert_eq!(eval(&mut saturating, "127 + 1"), Ok(v(127)));
+        assert_eq!(eval(&mut saturating, "-100 - 100"), Ok(v(-128)));
+
+        // Powers too large for the word are settled without being built.
+        let mut checked = word(WordSize::Qword, OverflowPolicy::Checked);
+        let mut wrapping = word(WordSize::Qword, OverflowPolicy::Wrapping);
+        let mut saturating = word(WordSize::Qword, OverflowPolicy::Saturating);
+        assert_eq!(eval(&mut checked, "3 ^ 100000000"), Err(EvalError::Overflow));
+        assert_eq!(eval(&mut wrapping, "2 ^ 64"), Ok(v(0)));
+        assert_eq!(eval(&mut saturating, "2 ^ 64"), Ok(v(i64::MAX)));
+        assert_eq!(eval(&mut saturating, "(-2) ^ 65"), Ok(v(i64::MIN)));
+        assert_eq!(eval(&mut checked, "1 << 64"), Err(EvalError::ShiftOutOfRange));
+        assert_eq!(eval(&mut wrapping, "1 << 65"), Ok(v(2)));
+        assert_eq!(eval(&mut saturating, "1 << 65"), Ok(v(0)));
+        // Bitwise operators never overflow.
+        assert_eq!(eval(&mut checked, "~0"), Ok(v(-1)));
+        assert_eq!(eval(&mut checked, "0 nor 0"), Ok(v(-1)));
+
+        // A digit that would overflow is not taken.
+        let mut calculator = word(WordSize::Qword, OverflowPolicy::Checked);
+        for digit in "92233720368547758070".bytes() {
+            calculator.add_token(Token::Digit(digit - b'0'));
+        }
+        assert_eq!(calculator.get_display(), "9223372036854775807");
+    }
 
-            let result = self.calculate_equation(&operators, &numbers);
-            self.token_stream.insert(start_idx, Token::Number(result));
-            println!("\nEvaluated parentheses -> {:?}", self.token_stream);
+    #[test]
+    fn toggles_the_sign() {
+        let mut calculator = Calculator::new();
+        for token in [
+            Token::Digit(5),
+            Token::ToggleSign,
+            Token::Operator(Operator::Addition),
+            Token::ToggleSign,
+            Token::Digit(3),
+        ] {
+            calculator.add_token(token);
         }
+        assert_eq!(calculator.get_display(), "-5 + - 3");
+        calculator.add_token(Token::Equals);
+        assert_eq!(calculator.evaluate(), Ok(v(-8)));
+        // A result changes sign in place.
+        calculator.add_token(Token::ToggleSign);
+        assert_eq!(calculator.get_display(), "8");
 
-        self.evaluate_full_expression()
+        let mut saturating = word(WordSize::Qword, OverflowPolicy::Saturating);
+        assert_eq!(eval(&mut saturating, "-9223372036854775807 - 2"), Ok(v(i64::MIN)));
+        saturating.add_token(Token::ToggleSign);
+        assert_eq!(saturating.get_display(), "9223372036854775807");
     }
 }
@@ -0,0 +1,67 @@
+// cli.rs
+use crate::calculator::Calculator;
+use crate::types::{AngleUnit, ArithmeticMode, RoundingMode};
+
+const USAGE: &str = "usage: cryocalc [--precision DIGITS] [--rounding half-even|half-up|to-zero|floor|ceiling] [--angle rad|deg|grad] EXPRESSION";
+
+/// Evaluates an expression given on the command line with decimal
+/// arithmetic, as the decimal view does, and prints the result. Returns the
+/// process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let mut calculator = Calculator::new().with_arithmetic(ArithmeticMode::Decimal);
+    let mut expression = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-p" | "--precision" => match args.next().and_then(|p| p.parse().ok()) {
+                Some(precision) if precision > 0 => calculator.set_precision(precision),
+                _ => return usage(),
+            },
+            "-r" | "--rounding" => match args.next().and_then(|r| rounding_mode(r)) {
+                Some(rounding) => calculator.set_rounding(rounding),
+                None => return usage(),
+            },
+            "-a" | "--angle" => match args.next().and_then(|a| AngleUnit::from_suffix(&a.to_ascii_lowercase())) {
+                Some(unit) => calculator.set_angle_unit(unit),
+                None => return usage(),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return 0;
+            }
+            _ => expression.push(arg.as_str()),
+        }
+    }
+    if expression.is_empty() {
+        return usage();
+    }
+
+    match calculator.evaluate_input(&expression.join(" ")) {
+        Ok(Some(result)) => {
+            println!("{}", calculator.format_number(&result));
+            0
+        }
+        // Nothing to print for a definition that is gone when we exit.
+        Ok(None) => 0,
+        Err(error) => {
+            eprintln!("cryocalc: {}", error);
+            1
+        }
+    }
+}
+
+fn rounding_mode(name: &str) -> Option<RoundingMode> {
+    match name.to_ascii_lowercase().as_str() {
+        "half-even" => Some(RoundingMode::HalfEven),
+        "half-up" => Some(RoundingMode::HalfUp),
+        "to-zero" => Some(RoundingMode::TowardZero),
+        "floor" => Some(RoundingMode::Floor),
+        "ceiling" => Some(RoundingMode::Ceiling),
+        _ => None,
+    }
+}
+
+fn usage() -> i32 {
+    eprintln!("{}", USAGE);
+    2
+}
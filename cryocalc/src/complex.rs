@@ -0,0 +1,195 @@
+use crate::{calculator::Calculator, types::AngleUnit, types::ArithmeticMode, types::Function, types::Token, types::Operator};
+
+use crate::error::EvalError;
+use crate::panels;
+use crate::theme::{MyTheme, ButtonClass};
+
+use iced::{
+    widget::{button, column, row, text_input, Text},
+    Element, Length,
+};
+
+use button::Catalog;
+
+#[derive(Default)]
+pub struct ComplexCalcState {
+    calculator: Calculator,
+    display_content: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ButtonPressed(Token),
+    DisplayContentChanged(String),
+    DisplayContentSubmitted,
+    CycleComplexFormat,
+}
+
+impl ComplexCalcState {
+    fn button<'a>(token: Token) -> Element<'a, Message> {
+        let my_theme = MyTheme;
+        button(Text::new(token.to_string()))
+            .on_press(Message::ButtonPressed(token))
+            .width(Length::Fill)
+            .style(move |_theme, status| my_theme.style(&ButtonClass::Primary, status))
+            .padding(16)
+            .into()
+    }
+
+    fn function_button<'a>(function: Function) -> Element<'a, Message> {
+        ComplexCalcState::button(Token::Function(function))
+    }
+
+    pub fn new() -> Self {
+        // Operands that turn out real are worked on as doubles.
+        ComplexCalcState {
+            calculator: Calculator::new().with_arithmetic(ArithmeticMode::Float),
+            display_content: String::new(),
+        }
+    }
+
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.calculator.set_angle_unit(unit);
+    }
+
+    pub fn expression(&self) -> Result<Vec<Token>, EvalError> {
+        self.calculator.expression()
+    }
+
+    pub fn set_expression(&mut self, expression: Result<Vec<Token>, EvalError>) {
+        self.display_content = match expression.and_then(|e| self.calculator.set_expression(e)) {
+            Ok(()) => self.calculator.get_display(),
+            Err(error) => error.to_string(),
+        };
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::ButtonPressed(token) => {
+                self.calculator.add_token(token.clone());
+                if let Token::Equals = token {
+                    self.display_content = match self.calculator.evaluate_complex() {
+                        Ok(result) => self.calculator.format_complex(&result),
+                        Err(error) => error.to_string(),
+                    };
+                } else {
+                    self.display_content = self.calculator.get_display();
+                }
+            }
+            Message::DisplayContentChanged(content) => self.display_content = content,
+            Message::DisplayContentSubmitted => {
+                match self.calculator.evaluate_complex_input(&self.display_content) {
+                    Ok(Some(result)) => self.display_content = self.calculator.format_complex(&result),
+                    // A function definition stays in the display as typed.
+                    Ok(None) => {}
+                    Err(error) => self.display_content = error.to_string(),
+                }
+            }
+            Message::CycleComplexFormat => {
+                let format = self.calculator.complex_format().next();
+                self.calculator.set_complex_format(format);
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let space = 5;
+        row![
+            column![
+                column![
+                    text_input("...", &self.display_content)
+                        .padding(space)
+                        .on_input(Message::DisplayContentChanged)
+                        .on_submit(Message::DisplayContentSubmitted),
+                    row![
+                        button(Text::new(format!(
+                            "Show: {}",
+                            self.calculator.complex_format()
+                        )))
+                        .on_press(Message::CycleComplexFormat)
+                        .padding(space),
+                    ]
+                    .spacing(space),
+                ]
+                .spacing(space)
+                .padding(15),
+                ComplexCalcState::keypad(),
+            ]
+            .width(Length::Fill)
+            .height(Length::Fill),
+            panels::complex_variables(&self.calculator, Message::ButtonPressed),
+        ]
+        .into()
+    }
+
+    fn keypad<'a>() -> Element<'a, Message> {
+        let space = 5;
+        column![
+            row![
+                ComplexCalcState::button(Token::Identifier("i".to_string())),
+                ComplexCalcState::function_button(Function::Abs),
+                ComplexCalcState::function_button(Function::Arg),
+                ComplexCalcState::function_button(Function::Conj),
+                ComplexCalcState::function_button(Function::Re),
+                ComplexCalcState::function_button(Function::Im),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                ComplexCalcState::function_button(Function::Sqrt),
+                ComplexCalcState::function_button(Function::Exp),
+                ComplexCalcState::function_button(Function::Ln),
+                ComplexCalcState::function_button(Function::Sin),
+                ComplexCalcState::function_button(Function::Cos),
+                ComplexCalcState::function_button(Function::Tan),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                ComplexCalcState::button(Token::ClearScreen),
+                ComplexCalcState::button(Token::LeftParenthesis),
+                ComplexCalcState::button(Token::Comma),
+                ComplexCalcState::button(Token::RightParenthesis),
+                ComplexCalcState::button(Token::ClearToken),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                ComplexCalcState::button(Token::Digit(7)),
+                ComplexCalcState::button(Token::Digit(8)),
+                ComplexCalcState::button(Token::Digit(9)),
+                ComplexCalcState::button(Token::Operator(Operator::Division)),
+                ComplexCalcState::button(Token::Operator(Operator::Power)),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                ComplexCalcState::button(Token::Digit(4)),
+                ComplexCalcState::button(Token::Digit(5)),
+                ComplexCalcState::button(Token::Digit(6)),
+                ComplexCalcState::button(Token::Operator(Operator::Multiplication)),
+                ComplexCalcState::button(Token::ToggleSign),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                ComplexCalcState::button(Token::Digit(1)),
+                ComplexCalcState::button(Token::Digit(2)),
+                ComplexCalcState::button(Token::Digit(3)),
+                ComplexCalcState::button(Token::Operator(Operator::Subtraction)),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                ComplexCalcState::button(Token::Digit(0)),
+                ComplexCalcState::button(Token::DecimalPoint),
+                ComplexCalcState::button(Token::Equals),
+                ComplexCalcState::button(Token::Operator(Operator::Addition)),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+        ]
+        .padding(10)
+        .into()
+    }
+}
@@ -0,0 +1,171 @@
+// complex_number.rs
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// A complex number on doubles, for complex mode. Functions with more than
+/// one value give the principal one, with the cut along the negative reals.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const I: Complex = Complex { re: 0.0, im: 1.0 };
+
+    pub fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    pub fn from_real(re: f64) -> Self {
+        Complex { re, im: 0.0 }
+    }
+
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        Complex::new(r * theta.cos(), r * theta.sin())
+    }
+
+    pub fn is_real(self) -> bool {
+        self.im == 0.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.re == 0.0 && self.im == 0.0
+    }
+
+    pub fn is_finite(self) -> bool {
+        self.re.is_finite() && self.im.is_finite()
+    }
+
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(self) -> Self {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn exp(self) -> Self {
+        Complex::from_polar(self.re.exp(), self.im)
+    }
+
+    pub fn ln(self) -> Self {
+        Complex::new(self.abs().ln(), self.arg())
+    }
+
+    /// Square root, exact on the imaginary axis for negative reals so that
+    /// sqrt(-1) is exactly i.
+    pub fn sqrt(self) -> Self {
+        if self.is_real() {
+            return if self.re >= 0.0 {
+                Complex::from_real(self.re.sqrt())
+            } else {
+                Complex::new(0.0, (-self.re).sqrt())
+            };
+        }
+        // Half-angle formulas, which lose nothing to cancellation.
+        let r = self.abs();
+        let re = ((r + self.re) / 2.0).sqrt();
+        let im = ((r - self.re) / 2.0).sqrt();
+        Complex::new(re, im.copysign(self.im))
+    }
+
+    /// Raises to a complex power. Whole exponents multiply out so that
+    /// i^2 is exactly -1; the rest go through exp(w ln z).
+    pub fn pow(self, exponent: Complex) -> Self {
+        if exponent.is_real() && exponent.re.fract() == 0.0 && exponent.re.abs() <= 1024.0 {
+            let n = exponent.re.abs() as u32;
+            let mut result = Complex::from_real(1.0);
+            let mut base = self;
+            let mut n = n;
+            while n > 0 {
+                if n & 1 == 1 {
+                    result = result * base;
+                }
+                base = base * base;
+                n >>= 1;
+            }
+            return if exponent.re < 0.0 { Complex::from_real(1.0) / result } else { result };
+        }
+        if self.is_zero() {
+            return Complex::default();
+        }
+        (exponent * self.ln()).exp()
+    }
+
+    pub fn sin(self) -> Self {
+        Complex::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+    }
+
+    pub fn cos(self) -> Self {
+        Complex::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh())
+    }
+
+    // asin z = -i ln(iz + sqrt(1 - z^2))
+    pub fn asin(self) -> Self {
+        let one = Complex::from_real(1.0);
+        -Complex::I * (Complex::I * self + (one - self * self).sqrt()).ln()
+    }
+
+    // atan z = i/2 (ln(1 - iz) - ln(1 + iz))
+    pub fn atan(self) -> Self {
+        let one = Complex::from_real(1.0);
+        let iz = Complex::I * self;
+        Complex::new(0.0, 0.5) * ((one - iz).ln() - (one + iz).ln())
+    }
+
+    pub fn scale(self, factor: f64) -> Self {
+        Complex::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, rhs: Complex) -> Complex {
+        Complex::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs: Complex) -> Complex {
+        Complex::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs: Complex) -> Complex {
+        Complex::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+/// Division by zero gives infinities; the evaluator checks before dividing.
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, rhs: Complex) -> Complex {
+        let denom = rhs.re * rhs.re + rhs.im * rhs.im;
+        Complex::new(
+            (self.re * rhs.re + self.im * rhs.im) / denom,
+            (self.im * rhs.re - self.re * rhs.im) / denom,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
@@ -0,0 +1,131 @@
+// constants.rs
+use num_bigint::BigInt;
+
+use crate::functions;
+use crate::rational::Rational;
+
+/// A named constant that expressions can refer to, with what the browser
+/// panel shows about it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Constant {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// SI units, empty for pure numbers.
+    pub units: &'static str,
+    definition: Definition,
+}
+
+// Physical constants are decimals, exact by definition of the SI units or
+// as published by CODATA. The mathematical ones are irrational and worked
+// out to whatever precision is asked for.
+#[derive(Debug, PartialEq, Eq)]
+enum Definition {
+    Decimal { mantissa: i64, exponent: i64 },
+    Pi,
+    Tau,
+    E,
+    Phi,
+    Sqrt2,
+}
+
+pub static CONSTANTS: [Constant; 11] = [
+    Constant {
+        name: "pi",
+        description: "Ratio of a circle's circumference to its diameter",
+        units: "",
+        definition: Definition::Pi,
+    },
+    Constant {
+        name: "e",
+        description: "Base of the natural logarithm",
+        units: "",
+        definition: Definition::E,
+    },
+    Constant {
+        name: "tau",
+        description: "One full turn in radians, 2π",
+        units: "",
+        definition: Definition::Tau,
+    },
+    Constant {
+        name: "phi",
+        description: "Golden ratio",
+        units: "",
+        definition: Definition::Phi,
+    },
+    Constant {
+        name: "sqrt2",
+        description: "Square root of two",
+        units: "",
+        definition: Definition::Sqrt2,
+    },
+    Constant {
+        name: "c",
+        description: "Speed of light in vacuum",
+        units: "m/s",
+        definition: Definition::Decimal { mantissa: 299_792_458, exponent: 0 },
+    },
+    Constant {
+        name: "h",
+        description: "Planck constant",
+        units: "J·s",
+        definition: Definition::Decimal { mantissa: 662_607_015, exponent: -42 },
+    },
+    Constant {
+        name: "k_B",
+        description: "Boltzmann constant",
+        units: "J/K",
+        definition: Definition::Decimal { mantissa: 1_380_649, exponent: -29 },
+    },
+    Constant {
+        name: "N_A",
+        description: "Avogadro constant",
+        units: "1/mol",
+        definition: Definition::Decimal { mantissa: 602_214_076, exponent: 15 },
+    },
+    Constant {
+        name: "G",
+        description: "Newtonian constant of gravitation",
+        units: "m³/(kg·s²)",
+        definition: Definition::Decimal { mantissa: 667_430, exponent: -16 },
+    },
+    Constant {
+        name: "e_charge",
+        description: "Elementary charge",
+        units: "C",
+        definition: Definition::Decimal { mantissa: 1_602_176_634, exponent: -28 },
+    },
+];
+
+/// Finds a constant by name. Names are case-sensitive, since `G` and `g`
+/// or `N_A` and `n_a` need not be the same thing.
+pub fn lookup(name: &str) -> Option<&'static Constant> {
+    CONSTANTS.iter().find(|constant| constant.name == name)
+}
+
+impl Constant {
+    /// Whether `value` is the constant itself rather than an approximation.
+    pub fn is_exact(&self) -> bool {
+        matches!(self.definition, Definition::Decimal { .. })
+    }
+
+    /// The value, good to at least `digits` significant digits.
+    pub fn value(&self, digits: u32) -> Rational {
+        // Every irrational here lies between one and ten, so places after
+        // the point and significant digits are nearly the same thing.
+        let places = digits as i64 + 1;
+        let two = Rational::from(2);
+        match self.definition {
+            Definition::Decimal { mantissa, exponent } => {
+                Rational::from_integer(BigInt::from(mantissa)) * Rational::power_of_ten(exponent)
+            }
+            Definition::Pi => functions::pi(places),
+            Definition::Tau => functions::pi(places + 1) * two,
+            Definition::E => functions::e(places),
+            Definition::Phi => {
+                (Rational::from(1) + functions::nth_root(&Rational::from(5), 2, places + 1)) / two
+            }
+            Definition::Sqrt2 => functions::nth_root(&two, 2, places),
+        }
+    }
+}
@@ -1,15 +1,16 @@
-use crate::{calculator::Calculator, types::Token, types::Operator};
-use std::boxed::Box;
+use crate::{calculator::Calculator, types::AngleUnit, types::ArithmeticMode, types::Token, types::Operator};
 
 
+use crate::error::EvalError;
+use crate::panels;
 use crate::theme::{MyTheme,ButtonClass};
 
 use iced::{
-    widget::{button, column, row, text_input, Button, Text},
-    Background, Border, Color, Element, Length, Renderer, Shadow, Theme,
+    widget::{button, column, row, text_input, Text},
+    Element, Length,
 };
 
-use button::{Catalog, Status, Style};
+use button::Catalog;
 
 #[derive(Default)]
 pub struct DecCalcState {
@@ -20,8 +21,14 @@ pub struct DecCalcState {
 #[derive(Debug, Clone)]
 pub enum Message {
     ButtonPressed(Token),
-    ForParent(Box<Message>),//SwitchMode,
     DisplayContentChanged(String),
+    DisplayContentSubmitted,
+    CycleDigitLimit,
+    CycleArithmetic,
+    CycleRationalFormat,
+    CyclePrecision,
+    CycleRounding,
+    Readout(panels::Readout),
 }
 
 
@@ -29,18 +36,16 @@ impl DecCalcState {
     fn button<'a>(token: Token) -> Element<'a, Message> {
         let my_theme = MyTheme;
         let str_from_token = token.to_string();
-        match token {
-            _ => button(Text::new(str_from_token))
-                .on_press(Message::ButtonPressed(token))
-                .width(Length::Fill)
-                .style(move |theme, status| my_theme.style(&ButtonClass::Primary, status))
-                .padding(16)
-                .into(),
-        }
+        button(Text::new(str_from_token))
+            .on_press(Message::ButtonPressed(token))
+            .width(Length::Fill)
+            .style(move |_theme, status| my_theme.style(&ButtonClass::Primary, status))
+            .padding(16)
+            .into()
     }
 
     pub fn new() -> Self {
-        let calculator = Calculator::new();
+        let calculator = Calculator::new().with_arithmetic(ArithmeticMode::Decimal);
         let display_content = "".to_string();
         DecCalcState {
             calculator,
@@ -48,87 +53,196 @@ impl DecCalcState {
         }
     }
 
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.calculator.set_angle_unit(unit);
+    }
+
+    pub fn expression(&self) -> Result<Vec<Token>, EvalError> {
+        self.calculator.expression()
+    }
+
+    // An expression that could not be carried leaves the session as it was,
+    // with the reason in the display.
+    pub fn set_expression(&mut self, expression: Result<Vec<Token>, EvalError>) {
+        self.display_content = match expression.and_then(|e| self.calculator.set_expression(e)) {
+            Ok(()) => self.calculator.get_display(),
+            Err(error) => error.to_string(),
+        };
+    }
+
     pub fn update(&mut self, message: Message) {
         match message {
             Message::ButtonPressed(token) => {
                 self.calculator.add_token(token.clone());
                 if let Token::Equals = token {
-                    let result = self.calculator.evaluate();
-                    self.display_content = result.to_string();
+                    self.display_content = match self.calculator.evaluate() {
+                        Ok(result) => self.calculator.format_number(&result),
+                        Err(error) => error.to_string(),
+                    };
                 } else {
                     self.display_content = self.calculator.get_display();
                 }
                 println!("\n{:?}", self.calculator.token_stream);
             }
-            Message::DisplayContentChanged(_) => {
-                todo!();
+            Message::DisplayContentChanged(content) => {
+                self.display_content = content;
+            }
+            Message::DisplayContentSubmitted => {
+                match self.calculator.evaluate_input(&self.display_content) {
+                    Ok(Some(result)) => self.display_content = self.calculator.format_number(&result),
+                    // A function definition stays in the display as typed.
+                    Ok(None) => {}
+                    Err(error) => self.display_content = error.to_string(),
+                }
+            }
+            Message::CycleDigitLimit => {
+                let limit = match self.calculator.digit_limit() {
+                    1_000 => 10_000,
+                    10_000 => 100_000,
+                    _ => 1_000,
+                };
+                self.calculator.set_digit_limit(limit);
+            }
+            Message::CycleArithmetic => {
+                let arithmetic = self.calculator.arithmetic().next();
+                self.calculator.set_arithmetic(arithmetic);
+                self.display_content = self.calculator.get_display();
+            }
+            Message::CycleRationalFormat => {
+                let format = self.calculator.rational_format().next();
+                self.calculator.set_rational_format(format);
+                self.display_content = self.calculator.get_display();
             }
-            Message::ForParent(_) => {
-                todo!();
+            Message::CyclePrecision => {
+                let precision = match self.calculator.precision() {
+                    16 => 34,
+                    34 => 50,
+                    50 => 100,
+                    _ => 16,
+                };
+                self.calculator.set_precision(precision);
             }
+            Message::CycleRounding => {
+                let rounding = self.calculator.rounding().next();
+                self.calculator.set_rounding(rounding);
+            }
+            // Copying and switching modes are up to the application.
+            Message::Readout(_) => {}
         }
     }
-    pub fn view(&self) -> Element<Message> {
+    pub fn view(&self) -> Element<'_, Message> {
         let space = 5;
-        column![
+        row![
             column![
-                text_input("...", &self.display_content)
-                    .padding(space)
-                    .on_input(Message::DisplayContentChanged),
-            ]
-            .padding(15),
-            column![
-                row![
-                    DecCalcState::button(Token::ClearScreen),
-                    DecCalcState::button(Token::LeftParenthesis),
-                    DecCalcState::button(Token::RightParenthesis),
-                    DecCalcState::button(Token::Operator(Operator::Division)),
-                    DecCalcState::button(Token::ClearToken),
-                ]
-                .width(iced::Length::Fill)
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    DecCalcState::button(Token::Number(7)),
-                    DecCalcState::button(Token::Number(8)),
-                    DecCalcState::button(Token::Number(9)),
-                    DecCalcState::button(Token::Operator(Operator::Multiplication))
-                ]
-                .width(Length::Fill)
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    DecCalcState::button(Token::Number(4)),
-                    DecCalcState::button(Token::Number(5)),
-                    DecCalcState::button(Token::Number(6)),
-                    DecCalcState::button(Token::Operator(Operator::Subtraction))
-                ]
-                .width(iced::Length::Fill)
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    DecCalcState::button(Token::Number(1)),
-                    DecCalcState::button(Token::Number(2)),
-                    DecCalcState::button(Token::Number(3)),
-                    DecCalcState::button(Token::Operator(Operator::Addition))
+                column![
+                    text_input("...", &self.display_content)
+                        .padding(space)
+                        .on_input(Message::DisplayContentChanged)
+                        .on_submit(Message::DisplayContentSubmitted),
+                    row![
+                        button(Text::new(format!(
+                            "Arithmetic: {}",
+                            self.calculator.arithmetic()
+                        )))
+                        .on_press(Message::CycleArithmetic)
+                        .padding(space),
+                        button(Text::new(format!(
+                            "Show: {}",
+                            self.calculator.rational_format()
+                        )))
+                        .on_press(Message::CycleRationalFormat)
+                        .padding(space),
+                        button(Text::new(format!(
+                            "Digit limit: {}",
+                            self.calculator.digit_limit()
+                        )))
+                        .on_press(Message::CycleDigitLimit)
+                        .padding(space),
+                    ]
+                    .spacing(space),
+                    row![
+                        button(Text::new(format!(
+                            "Precision: {}",
+                            self.calculator.precision()
+                        )))
+                        .on_press(Message::CyclePrecision)
+                        .padding(space),
+                        button(Text::new(format!(
+                            "Rounding: {}",
+                            self.calculator.rounding()
+                        )))
+                        .on_press(Message::CycleRounding)
+                        .padding(space),
+                    ]
+                    .spacing(space),
+                    panels::readout(&self.calculator, Message::Readout),
                 ]
-                .width(iced::Length::Fill)
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    DecCalcState::button(Token::Number(0)),
-                    DecCalcState::button(Token::Number(0)),
-                    DecCalcState::button(Token::Number(9)),
-                    DecCalcState::button(Token::Equals)
+                .spacing(space)
+                .padding(15),
+                column![
+                    row![
+                        DecCalcState::button(Token::ClearScreen),
+                        DecCalcState::button(Token::LeftParenthesis),
+                        DecCalcState::button(Token::RightParenthesis),
+                        DecCalcState::button(Token::Operator(Operator::Division)),
+                        DecCalcState::button(Token::ClearToken),
+                    ]
+                    .width(iced::Length::Fill)
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        DecCalcState::button(Token::Operator(Operator::Remainder)),
+                        DecCalcState::button(Token::Operator(Operator::Modulo)),
+                        DecCalcState::button(Token::Operator(Operator::EuclideanDivision)),
+                        DecCalcState::button(Token::Operator(Operator::Power)),
+                        DecCalcState::button(Token::Percent),
+                    ]
+                    .width(iced::Length::Fill)
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        DecCalcState::button(Token::Digit(7)),
+                        DecCalcState::button(Token::Digit(8)),
+                        DecCalcState::button(Token::Digit(9)),
+                        DecCalcState::button(Token::Operator(Operator::Multiplication))
+                    ]
+                    .width(Length::Fill)
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        DecCalcState::button(Token::Digit(4)),
+                        DecCalcState::button(Token::Digit(5)),
+                        DecCalcState::button(Token::Digit(6)),
+                        DecCalcState::button(Token::Operator(Operator::Subtraction))
+                    ]
+                    .width(iced::Length::Fill)
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        DecCalcState::button(Token::Digit(1)),
+                        DecCalcState::button(Token::Digit(2)),
+                        DecCalcState::button(Token::Digit(3)),
+                        DecCalcState::button(Token::Operator(Operator::Addition))
+                    ]
+                    .width(iced::Length::Fill)
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        DecCalcState::button(Token::ToggleSign),
+                        DecCalcState::button(Token::Digit(0)),
+                        DecCalcState::button(Token::DecimalPoint),
+                        DecCalcState::button(Token::Equals)
+                    ]
+                    .width(iced::Length::Fill)
+                    .spacing(space * 2)
+                    .padding(space),
                 ]
-                .width(iced::Length::Fill)
-                .spacing(space * 2)
-                .padding(space),
+                .padding(10)
             ]
-            .padding(10)
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill),
+            panels::variables(&self.calculator, Message::ButtonPressed),
         ]
-        .width(iced::Length::Fill)
-        .height(iced::Length::Fill)
         .into()
     }
 }
@@ -0,0 +1,53 @@
+// error.rs
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    EmptyExpression,
+    UnbalancedParentheses,
+    TrailingOperator,
+    UnexpectedToken,
+    InvalidCharacter(char),
+    DivisionByZero,
+    NegativeExponent,
+    ShiftOutOfRange,
+    Overflow,
+    RotateUnbounded,
+    DigitLimit,
+    NotAnInteger,
+    NotReal,
+    Undefined,
+    ArgumentCount(String),
+    UnknownName(String),
+    ReadOnly(String),
+    RecursionLimit,
+    NestingLimit,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::EmptyExpression => write!(f, "Empty expression"),
+            EvalError::UnbalancedParentheses => write!(f, "Unbalanced parentheses"),
+            EvalError::TrailingOperator => write!(f, "Missing operand"),
+            EvalError::UnexpectedToken => write!(f, "Syntax error"),
+            EvalError::InvalidCharacter(c) => write!(f, "Invalid character '{}'", c),
+            EvalError::DivisionByZero => write!(f, "Division by zero"),
+            EvalError::NegativeExponent => write!(f, "Negative exponent"),
+            EvalError::ShiftOutOfRange => write!(f, "Shift out of range"),
+            EvalError::Overflow => write!(f, "Overflow"),
+            EvalError::RotateUnbounded => write!(f, "Rotate needs a word size"),
+            EvalError::DigitLimit => write!(f, "Too many digits"),
+            EvalError::NotAnInteger => write!(f, "Not an integer"),
+            EvalError::NotReal => write!(f, "Not a real number"),
+            EvalError::Undefined => write!(f, "Undefined"),
+            EvalError::ArgumentCount(name) => write!(f, "Wrong number of arguments to {}", name),
+            EvalError::UnknownName(name) => write!(f, "Unknown name {}", name),
+            EvalError::ReadOnly(name) => write!(f, "Cannot assign to {}", name),
+            EvalError::RecursionLimit => write!(f, "Too much recursion"),
+            EvalError::NestingLimit => write!(f, "Too deeply nested"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
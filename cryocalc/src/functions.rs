@@ -0,0 +1,347 @@
+// functions.rs
+use num_bigint::BigInt;
+use std::ops::Neg;
+
+use num_traits::{One, ToPrimitive};
+
+use crate::error::EvalError;
+use crate::rational::Rational;
+use crate::types::{AngleUnit, Function, RoundingMode};
+
+/// Digits carried beyond the requested precision, so that rounding along
+/// the way never reaches the digits that are kept.
+const GUARD_DIGITS: i64 = 10;
+
+/// Approximates a transcendental function or root to at least `digits`
+/// significant digits, with angles in `unit`. Results of `exp` with more
+/// than `max_exponent` digits before or after the point are refused rather
+/// than computed.
+pub fn approximate(
+    function: Function,
+    x: &Rational,
+    unit: AngleUnit,
+    digits: u32,
+    max_exponent: u64,
+) -> Result<Rational, EvalError> {
+    let digits = digits as i64 + GUARD_DIGITS;
+    let from_radians = |angle: Rational| convert(&angle, AngleUnit::Radians, unit, digits);
+    match function {
+        Function::Sin => Ok(sin_cos(x, unit, digits)?.0),
+        Function::Cos => Ok(sin_cos(x, unit, digits)?.1),
+        Function::Tan => {
+            let (sin, cos) = sin_cos(x, unit, digits)?;
+            if cos.is_zero() {
+                return Err(EvalError::Undefined);
+            }
+            Ok(sin / cos)
+        }
+        Function::Asin => Ok(from_radians(asin(x, digits)?)),
+        Function::Acos => Ok(from_radians(pi(digits) / Rational::from(2) - asin(x, digits)?)),
+        Function::Atan => Ok(from_radians(atan(x, digits))),
+        Function::Exp => exp(x, digits, max_exponent),
+        Function::Ln => ln(x, digits),
+        Function::Log10 => Ok(ln(x, digits)? / ln(&Rational::from(10), digits)?),
+        Function::Log2 => Ok(ln(x, digits)? / ln(&Rational::from(2), digits)?),
+        Function::Sqrt => root(x, 2, digits),
+        Function::Cbrt => root(x, 3, digits),
+        _ => unreachable!("{:?} is computed exactly", function),
+    }
+}
+
+/// The same functions on doubles, for floating-point arithmetic.
+pub fn approximate_f64(function: Function, x: f64, unit: AngleUnit) -> f64 {
+    let from_radians = |angle: f64| convert_angle_f64(angle, AngleUnit::Radians, unit);
+    match function {
+        Function::Sin => sin_cos_f64(x, unit).0,
+        Function::Cos => sin_cos_f64(x, unit).1,
+        Function::Tan => match sin_cos_f64(x, unit) {
+            (_, 0.0) => f64::NAN,
+            (sin, cos) => sin / cos,
+        },
+        Function::Asin => from_radians(x.asin()),
+        Function::Acos => from_radians(x.acos()),
+        Function::Atan => from_radians(x.atan()),
+        Function::Exp => x.exp(),
+        Function::Ln => x.ln(),
+        Function::Log10 => x.log10(),
+        Function::Log2 => x.log2(),
+        Function::Sqrt => x.sqrt(),
+        Function::Cbrt => x.cbrt(),
+        _ => unreachable!("{:?} is computed exactly", function),
+    }
+}
+
+/// Converts an angle from one unit to another. Between degrees and
+/// gradians this is exact; radians go through pi to `digits` significant
+/// digits.
+pub fn convert_angle(x: &Rational, from: AngleUnit, to: AngleUnit, digits: u32) -> Rational {
+    convert(x, from, to, digits as i64 + GUARD_DIGITS)
+}
+
+pub fn convert_angle_f64(x: f64, from: AngleUnit, to: AngleUnit) -> f64 {
+    let half_turn = |unit: AngleUnit| match unit.right_angle() {
+        Some(right_angle) => 2.0 * right_angle as f64,
+        None => std::f64::consts::PI,
+    };
+    if from == to { x } else { x * half_turn(to) / half_turn(from) }
+}
+
+/// Pi to `places` digits after the point, by Machin's formula.
+pub fn pi(places: i64) -> Rational {
+    let places = places + 2;
+    let atan_fifth = arctan_series(&Rational::new(1.into(), 5.into()), places, true);
+    let atan_239th = arctan_series(&Rational::new(1.into(), 239.into()), places, true);
+    round(Rational::from(16) * atan_fifth - Rational::from(4) * atan_239th, places)
+}
+
+/// Euler's number to `places` digits after the point, summing 1/k!.
+pub fn e(places: i64) -> Rational {
+    let epsilon = Rational::power_of_ten(-places - 2);
+    let mut term = Rational::from(1);
+    let mut sum = term.clone();
+    let mut k = 1i64;
+    while term >= epsilon {
+        term = round(term / Rational::from(k), places + 4);
+        sum = sum + term.clone();
+        k += 1;
+    }
+    round(sum, places)
+}
+
+fn round(x: Rational, places: i64) -> Rational {
+    x.round_places(places, RoundingMode::HalfEven)
+}
+
+// Places needed after the point for `digits` significant digits of a value
+// of this size.
+fn places_for(x: &Rational, digits: i64) -> i64 {
+    digits - x.decimal_exponent().min(0)
+}
+
+// z - z^3/3 + z^5/5 - ... for arctan, or with every sign positive for
+// artanh. Both converge quickly for the small |z| they are given here.
+fn arctan_series(z: &Rational, places: i64, alternate: bool) -> Rational {
+    let epsilon = Rational::power_of_ten(-places - 2);
+    let z_squared = z.clone() * z.clone();
+    let mut power = z.clone();
+    let mut sum = Rational::default();
+    let mut k = 0i64;
+    loop {
+        let term = power.clone() / Rational::from(2 * k + 1);
+        if term.abs() < epsilon {
+            return round(sum, places);
+        }
+        sum = if alternate && k % 2 == 1 { sum - term } else { sum + term };
+        power = round(power * z_squared.clone(), places + 4);
+        k += 1;
+    }
+}
+
+// Taylor series for sin (`first` = 1) or cos (`first` = 0) around zero.
+fn taylor_sin_cos(r: &Rational, places: i64, first: i64) -> Rational {
+    let epsilon = Rational::power_of_ten(-places - 2);
+    let r_squared = r.clone() * r.clone();
+    let mut term = if first == 1 { r.clone() } else { Rational::from(1) };
+    let mut sum = term.clone();
+    let mut k = first;
+    while term.abs() >= epsilon {
+        term = round(
+            -(term * r_squared.clone()) / Rational::from((k + 1) * (k + 2)),
+            places + 4,
+        );
+        sum = sum + term.clone();
+        k += 2;
+    }
+    round(sum, places)
+}
+
+// Half a turn in each unit, with pi to `digits` places for radians.
+fn half_turn(unit: AngleUnit, digits: i64) -> Rational {
+    match unit.right_angle() {
+        Some(right_angle) => Rational::from(2 * right_angle),
+        None => pi(digits),
+    }
+}
+
+fn convert(x: &Rational, from: AngleUnit, to: AngleUnit, digits: i64) -> Rational {
+    if from == to {
+        return x.clone();
+    }
+    // Pi needs as many more digits as x has before the point.
+    let digits = digits + x.decimal_exponent().max(0);
+    x.clone() * half_turn(to, digits) / half_turn(from, digits)
+}
+
+// Sine and cosine a number of quarter turns further round.
+fn rotate<T: Neg<Output = T>>((sin, cos): (T, T), quarter_turns: u8) -> (T, T) {
+    match quarter_turns % 4 {
+        0 => (sin, cos),
+        1 => (cos, -sin),
+        2 => (-sin, -cos),
+        _ => (-cos, sin),
+    }
+}
+
+// Sine and cosine after taking out whole quarter turns. In degrees and
+// gradians they come off exactly, so that sin(180) is exactly zero.
+fn sin_cos(x: &Rational, unit: AngleUnit, digits: i64) -> Result<(Rational, Rational), EvalError> {
+    if x.is_zero() {
+        return Ok((Rational::default(), Rational::from(1)));
+    }
+    // Pi needs as many more digits as x has before the point, which past
+    // the precision soon takes longer to work out than anyone would wait.
+    if unit == AngleUnit::Radians && x.decimal_exponent() > digits {
+        return Err(EvalError::DigitLimit);
+    }
+    let places = digits + x.decimal_exponent().max(0);
+    let right_angle = match unit.right_angle() {
+        Some(right_angle) => Rational::from(right_angle),
+        None => pi(places + 2) / Rational::from(2),
+    };
+    let quarter_turns = (x.clone() / right_angle.clone()).round(RoundingMode::HalfEven);
+    let r = x.clone() - right_angle * Rational::from(quarter_turns.clone());
+    let r = match unit {
+        AngleUnit::Radians => round(r, places),
+        _ => convert(&r, unit, AngleUnit::Radians, digits + 2),
+    };
+    let quarter_turns = (quarter_turns % 4u8 + 4u8).to_u8().unwrap_or_default();
+    if r.is_zero() {
+        return Ok(rotate((Rational::default(), Rational::from(1)), quarter_turns));
+    }
+    let places = places_for(&r, digits);
+    let sin = taylor_sin_cos(&r, places, 1);
+    let cos = taylor_sin_cos(&r, places, 0);
+    Ok(rotate((sin, cos), quarter_turns))
+}
+
+fn sin_cos_f64(x: f64, unit: AngleUnit) -> (f64, f64) {
+    let Some(right_angle) = unit.right_angle() else {
+        return x.sin_cos();
+    };
+    let right_angle = right_angle as f64;
+    let quarter_turns = (x / right_angle).round();
+    let r = (x - quarter_turns * right_angle) * std::f64::consts::FRAC_PI_2 / right_angle;
+    rotate(r.sin_cos(), quarter_turns.rem_euclid(4.0) as u8)
+}
+
+fn atan(x: &Rational, digits: i64) -> Rational {
+    if x.is_zero() {
+        return Rational::default();
+    }
+    if x.is_negative() {
+        return -atan(&-x.clone(), digits);
+    }
+    if *x > Rational::from(1) {
+        return pi(digits) / Rational::from(2) - atan(&x.recip(), digits);
+    }
+
+    // Halve the angle until the series converges quickly:
+    // atan(x) = 2 atan(x / (1 + sqrt(1 + x^2))).
+    let places = places_for(x, digits);
+    let tenth = Rational::new(1.into(), 10.into());
+    let mut x = x.clone();
+    let mut halvings = 0u32;
+    while x > tenth {
+        let hypotenuse = nth_root(&(Rational::from(1) + x.clone() * x.clone()), 2, places + 2);
+        x = round(x / (Rational::from(1) + hypotenuse), places + 2);
+        halvings += 1;
+    }
+    arctan_series(&x, places + 2, true) * Rational::from(1i64 << halvings)
+}
+
+fn asin(x: &Rational, digits: i64) -> Result<Rational, EvalError> {
+    let one = Rational::from(1);
+    match x.abs().cmp(&one) {
+        std::cmp::Ordering::Greater => Err(EvalError::Undefined),
+        std::cmp::Ordering::Equal => {
+            let half_pi = pi(digits) / Rational::from(2);
+            Ok(if x.is_negative() { -half_pi } else { half_pi })
+        }
+        std::cmp::Ordering::Less => {
+            let cos = root(&(one - x.clone() * x.clone()), 2, digits)?;
+            Ok(atan(&(x.clone() / cos), digits))
+        }
+    }
+}
+
+// Sums the series for a small argument, then squares the result back up:
+// e^x = (e^(x / 2^k))^(2^k). Each squaring costs a little precision.
+fn exp(x: &Rational, digits: i64, max_exponent: u64) -> Result<Rational, EvalError> {
+    if x.is_zero() {
+        return Ok(Rational::from(1));
+    }
+    // e^x has about |x| / ln 10 digits before or after the point.
+    let limit = (max_exponent as f64 * std::f64::consts::LN_10) as u64;
+    if x.abs() > Rational::from_integer(BigInt::from(limit)) {
+        return Err(EvalError::DigitLimit);
+    }
+
+    let halvings = x.abs().to_integer().bits() + 8;
+    let places = digits + halvings as i64 * 3 / 10 + 2;
+    let r = x.clone() / Rational::from_integer(BigInt::one() << halvings);
+    let epsilon = Rational::power_of_ten(-places - 2);
+    let mut term = Rational::from(1);
+    let mut sum = term.clone();
+    let mut k = 1i64;
+    while term.abs() >= epsilon {
+        term = round(term * r.clone() / Rational::from(k), places + 4);
+        sum = sum + term.clone();
+        k += 1;
+    }
+    for _ in 0..halvings {
+        sum = (sum.clone() * sum).round_significant(places as u32, RoundingMode::HalfEven);
+    }
+    Ok(sum)
+}
+
+// ln x = ln m + e ln 2 with x = m 2^e and m near one, and
+// ln m = 2 artanh((m - 1) / (m + 1)).
+fn ln(x: &Rational, digits: i64) -> Result<Rational, EvalError> {
+    if x.is_negative() || x.is_zero() {
+        return Err(EvalError::Undefined);
+    }
+    let one = Rational::from(1);
+    if *x == one {
+        return Ok(Rational::default());
+    }
+
+    let exponent = x.numer().bits() as i64 - x.denom().bits() as i64;
+    let power = Rational::from_integer(BigInt::one() << exponent.unsigned_abs());
+    let m = if exponent >= 0 { x.clone() / power } else { x.clone() * power };
+
+    // Results near zero need more places, and e ln 2 needs as many extra
+    // digits as e has. m keeps every digit of x, which the series has no
+    // use for beyond the places it works to.
+    let places = places_for(&(x.clone() - one.clone()), digits);
+    let extra = exponent.unsigned_abs().to_string().len() as i64;
+    let m = round(m, places + 2);
+    let two = Rational::from(2);
+    let z = (m.clone() - one.clone()) / (m + one);
+    let ln_m = two.clone() * arctan_series(&z, places + 1, false);
+    let third = Rational::new(1.into(), 3.into());
+    let ln_2 = two * arctan_series(&third, places + extra + 1, false);
+    Ok(round(ln_m + ln_2 * Rational::from(exponent), places))
+}
+
+// Square and cube roots, exact whenever numerator and denominator are
+// perfect powers.
+fn root(x: &Rational, n: u32, digits: i64) -> Result<Rational, EvalError> {
+    if x.is_negative() {
+        return match n % 2 {
+            0 => Err(EvalError::Undefined),
+            _ => Ok(-root(&x.abs(), n, digits)?),
+        };
+    }
+    let (numer, denom) = (x.numer().nth_root(n), x.denom().nth_root(n));
+    if numer.pow(n) == *x.numer() && denom.pow(n) == *x.denom() {
+        return Ok(Rational::new(numer, denom));
+    }
+    let places = digits - x.decimal_exponent() / n as i64 + 1;
+    Ok(nth_root(x, n, places))
+}
+
+/// The root of x truncated to `places` digits after the point.
+pub fn nth_root(x: &Rational, n: u32, places: i64) -> Rational {
+    let scaled = (x.clone() * Rational::power_of_ten(n as i64 * places)).round(RoundingMode::Floor);
+    Rational::from_integer(scaled.nth_root(n)) / Rational::power_of_ten(places)
+}
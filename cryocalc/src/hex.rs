@@ -1,7 +1,10 @@
-use crate::{calculator::Calculator, types::Token, types::Operator};
+use crate::{calculator::Calculator, types::AngleUnit, types::Token, types::Operator, types::WordSize};
+
+use crate::error::EvalError;
+use crate::panels;
 
 use iced::{
-    widget::{button, column, row, text_input, Button, Text},
+    widget::{button, column, row, text_input, Text},
     Element, Length,
 };
 
@@ -15,21 +18,16 @@ pub struct HexCalcState {
 pub enum Message {
     ButtonPressed(Token),
     DisplayContentChanged(String),
+    DisplayContentSubmitted,
+    CycleOverflowPolicy,
+    CycleWordSize,
+    ToggleSigned,
+    Readout(panels::Readout),
 }
 
 impl HexCalcState {
     fn button<'a>(token: Token) -> Element<'a, Message> {
-        let label = match token {
-            Token::Number(0xA) => "A".to_string(),
-            Token::Number(0xB) => "B".to_string(),
-            Token::Number(0xC) => "C".to_string(),
-            Token::Number(0xD) => "D".to_string(),
-            Token::Number(0xE) => "E".to_string(),
-            Token::Number(0xF) => "F".to_string(),
-            _ => token.to_string(),
-        };
-        
-        button(Text::new(label))
+        button(Text::new(token.to_string()))
             .on_press(Message::ButtonPressed(token))
             .width(Length::Fill)
             .padding(16)
@@ -38,95 +36,199 @@ impl HexCalcState {
 
     pub fn new() -> Self {
         HexCalcState {
-            calculator: Calculator::new().with_base(16),
+            calculator: Calculator::new()
+                .with_base(16)
+                .with_word_size(WordSize::Qword),
             display_content: String::new(),
         }
     }
 
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.calculator.set_angle_unit(unit);
+    }
+
+    pub fn expression(&self) -> Result<Vec<Token>, EvalError> {
+        self.calculator.expression()
+    }
+
+    pub fn set_expression(&mut self, expression: Result<Vec<Token>, EvalError>) {
+        self.display_content = match expression.and_then(|e| self.calculator.set_expression(e)) {
+            Ok(()) => self.calculator.get_display(),
+            Err(error) => error.to_string(),
+        };
+    }
+
+    pub fn calculator(&self) -> &Calculator {
+        &self.calculator
+    }
+
     pub fn update(&mut self, message: Message) {
         match message {
             Message::ButtonPressed(token) => {
                 if token.is_valid_for_base(16) {
                     self.calculator.add_token(token.clone());
                     if let Token::Equals = token {
-                        let result = self.calculator.evaluate();
-                        self.display_content = format!("{:X}", result);
+                        self.display_content = match self.calculator.evaluate() {
+                            Ok(result) => self.calculator.format_number(&result),
+                            Err(error) => error.to_string(),
+                        };
                     } else {
                         self.display_content = self.calculator.get_display();
                     }
                 }
             }
-            Message::DisplayContentChanged(_) => todo!(),
+            Message::DisplayContentChanged(content) => self.display_content = content,
+            Message::DisplayContentSubmitted => {
+                match self.calculator.evaluate_input(&self.display_content) {
+                    Ok(Some(result)) => self.display_content = self.calculator.format_number(&result),
+                    // A function definition stays in the display as typed.
+                    Ok(None) => {}
+                    Err(error) => self.display_content = error.to_string(),
+                }
+            }
+            Message::CycleOverflowPolicy => {
+                let policy = self.calculator.overflow_policy().next();
+                self.calculator.set_overflow_policy(policy);
+            }
+            Message::CycleWordSize => {
+                let word_size = self.calculator.word_size().next();
+                self.calculator.set_word_size(word_size);
+                self.display_content = self.calculator.get_display();
+            }
+            Message::ToggleSigned => {
+                let signed = !self.calculator.is_signed();
+                self.calculator.set_signed(signed);
+                self.display_content = self.calculator.get_display();
+            }
+            // Copying and switching modes are up to the application.
+            Message::Readout(_) => {}
         }
     }
 
-    pub fn view(&self) -> Element<Message> {
+    pub fn view(&self) -> Element<'_, Message> {
         let space = 5;
-        column![
-            column![
-                text_input("...", &self.display_content)
-                    .padding(space)
-                    .on_input(Message::DisplayContentChanged),
-            ]
-            .padding(15),
+        row![
             column![
-                row![
-                    HexCalcState::button(Token::ClearScreen),
-                    HexCalcState::button(Token::LeftParenthesis),
-                    HexCalcState::button(Token::RightParenthesis),
-                    HexCalcState::button(Token::Operator(Operator::Division)),
-                    HexCalcState::button(Token::ClearToken),
-                ]
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    HexCalcState::button(Token::Number(7)),
-                    HexCalcState::button(Token::Number(8)),
-                    HexCalcState::button(Token::Number(9)),
-                    HexCalcState::button(Token::Operator(Operator::Multiplication))
-                ]
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    HexCalcState::button(Token::Number(4)),
-                    HexCalcState::button(Token::Number(5)),
-                    HexCalcState::button(Token::Number(6)),
-                    HexCalcState::button(Token::Operator(Operator::Subtraction))
-                ]
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    HexCalcState::button(Token::Number(1)),
-                    HexCalcState::button(Token::Number(2)),
-                    HexCalcState::button(Token::Number(3)),
-                    HexCalcState::button(Token::Operator(Operator::Addition))
-                ]
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    HexCalcState::button(Token::Number(0xA)),
-                    HexCalcState::button(Token::Number(0xB)),
-                    HexCalcState::button(Token::Number(0xC)),
-                    HexCalcState::button(Token::Operator(Operator::Multiplication))
-                ]
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    HexCalcState::button(Token::Number(0xD)),
-                    HexCalcState::button(Token::Number(0xE)),
-                    HexCalcState::button(Token::Number(0xF)),
-                    HexCalcState::button(Token::Operator(Operator::Subtraction))
+                column![
+                    text_input("...", &self.display_content)
+                        .padding(space)
+                        .on_input(Message::DisplayContentChanged)
+                        .on_submit(Message::DisplayContentSubmitted),
+                    row![
+                        button(Text::new(format!(
+                            "Overflow: {}",
+                            self.calculator.overflow_policy()
+                        )))
+                        .on_press(Message::CycleOverflowPolicy)
+                        .padding(space),
+                        button(Text::new(self.calculator.word_size().to_string()))
+                            .on_press(Message::CycleWordSize)
+                            .padding(space),
+                        button(Text::new(if self.calculator.is_signed() {
+                            "Signed"
+                        } else {
+                            "Unsigned"
+                        }))
+                        .on_press(Message::ToggleSigned)
+                        .padding(space),
+                    ]
+                    .spacing(space),
+                    panels::readout(&self.calculator, Message::Readout),
                 ]
-                .spacing(space * 2)
-                .padding(space),
-                row![
-                    HexCalcState::button(Token::Number(0)),
-                    HexCalcState::button(Token::Equals)
+                .spacing(space)
+                .padding(15),
+                column![
+                    row![
+                        HexCalcState::button(Token::ClearScreen),
+                        HexCalcState::button(Token::LeftParenthesis),
+                        HexCalcState::button(Token::RightParenthesis),
+                        HexCalcState::button(Token::Operator(Operator::Division)),
+                        HexCalcState::button(Token::ClearToken),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::Operator(Operator::Remainder)),
+                        HexCalcState::button(Token::Operator(Operator::Modulo)),
+                        HexCalcState::button(Token::Operator(Operator::EuclideanDivision)),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::Operator(Operator::And)),
+                        HexCalcState::button(Token::Operator(Operator::Or)),
+                        HexCalcState::button(Token::Operator(Operator::Xor)),
+                        HexCalcState::button(Token::Not),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::Operator(Operator::Nand)),
+                        HexCalcState::button(Token::Operator(Operator::Nor)),
+                        HexCalcState::button(Token::Operator(Operator::RotateLeft)),
+                        HexCalcState::button(Token::Operator(Operator::RotateRight)),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::Operator(Operator::ShiftLeft)),
+                        HexCalcState::button(Token::Operator(Operator::LogicalShiftRight)),
+                        HexCalcState::button(Token::Operator(Operator::ArithmeticShiftRight)),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::Digit(7)),
+                        HexCalcState::button(Token::Digit(8)),
+                        HexCalcState::button(Token::Digit(9)),
+                        HexCalcState::button(Token::Operator(Operator::Multiplication))
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::Digit(4)),
+                        HexCalcState::button(Token::Digit(5)),
+                        HexCalcState::button(Token::Digit(6)),
+                        HexCalcState::button(Token::Operator(Operator::Subtraction))
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::Digit(1)),
+                        HexCalcState::button(Token::Digit(2)),
+                        HexCalcState::button(Token::Digit(3)),
+                        HexCalcState::button(Token::Operator(Operator::Addition))
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::Digit(0xA)),
+                        HexCalcState::button(Token::Digit(0xB)),
+                        HexCalcState::button(Token::Digit(0xC)),
+                        HexCalcState::button(Token::Operator(Operator::Multiplication))
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::Digit(0xD)),
+                        HexCalcState::button(Token::Digit(0xE)),
+                        HexCalcState::button(Token::Digit(0xF)),
+                        HexCalcState::button(Token::Operator(Operator::Subtraction))
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        HexCalcState::button(Token::ToggleSign),
+                        HexCalcState::button(Token::Digit(0)),
+                        HexCalcState::button(Token::Operator(Operator::Power)),
+                        HexCalcState::button(Token::Equals)
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
                 ]
-                .spacing(space * 2)
-                .padding(space),
-            ]
-            .padding(10)
+                .padding(10)
+            ],
+            panels::variables(&self.calculator, Message::ButtonPressed),
         ]
         .into()
     }
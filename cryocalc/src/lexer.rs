@@ -0,0 +1,363 @@
+// lexer.rs
+use crate::constants;
+use crate::error::EvalError;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::types::{AngleUnit, Function, Integer, Operator, Token, Value};
+
+/// Splits typed input into the same tokens the keypad produces. Numbers are
+/// read in `base` and come out non-negative; outside decimal they are bit
+/// patterns that the calculator still has to fit to its word size. Decimal
+/// numbers may have a fractional part, with a repeating block in brackets
+/// as results are displayed: `0.1[6]` is 1/6. They may also end in an
+/// exponent, as in `1.5e-7`, as long as it has no more than `digit_limit`
+/// digits' worth of zeros to add.
+pub fn tokenize(input: &str, base: u8, digit_limit: u64) -> Result<Vec<Token>, EvalError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if let Some(radix) = radix_prefix(&chars, base) {
+            chars.nth(1);
+            let digits = take_digits(&mut chars, radix);
+            tokens.push(number_token(&digits, radix)?);
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '.' && base == 10) {
+            let digits = take_digits(&mut chars, base);
+            if base != 10 {
+                tokens.push(number_token(&digits, base)?);
+                continue;
+            }
+            let (mut fraction, mut repeating) = (String::new(), String::new());
+            if chars.peek() == Some(&'.') {
+                chars.next();
+                fraction = take_digits(&mut chars, base);
+                repeating = take_repeating(&mut chars)?;
+            }
+            let mut token = decimal_token(&digits, &fraction, &repeating)?;
+            if let Some(exponent) = take_exponent(&mut chars)? {
+                if exponent.unsigned_abs() > digit_limit {
+                    return Err(EvalError::DigitLimit);
+                }
+                let scale = Value::checked_power_of_ten(exponent).ok_or(EvalError::DigitLimit)?;
+                if let Token::Number(n) = token {
+                    token = Token::Number(n * scale);
+                }
+            }
+            tokens.push(token);
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            let token = word_token(&word, base)?;
+            // A name right after a value multiplies, like a parenthesis:
+            // 2pi, 3sin(x), 4x.
+            if matches!(token, Token::Function(_) | Token::Constant(_) | Token::Identifier(_))
+                && ends_value(tokens.last())
+            {
+                tokens.push(Token::Operator(Operator::Multiplication));
+            }
+            tokens.push(token);
+            continue;
+        }
+
+        chars.next();
+        let token = match c {
+            '+' => Token::Operator(Operator::Addition),
+            '-' => Token::Operator(Operator::Subtraction),
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                Token::Operator(Operator::Power)
+            }
+            '*' | '×' => Token::Operator(Operator::Multiplication),
+            '/' | '÷' => Token::Operator(Operator::Division),
+            '%' if percent_follows(&chars, base) => Token::Percent,
+            '%' => Token::Operator(Operator::Remainder),
+            '&' => Token::Operator(Operator::And),
+            '|' => Token::Operator(Operator::Or),
+            '~' => Token::Not,
+            '<' if chars.peek() == Some(&'<') => {
+                chars.next();
+                Token::Operator(Operator::ShiftLeft)
+            }
+            '>' if chars.peek() == Some(&'>') => {
+                chars.next();
+                // `>>` copies the top bit of the word, signed or not, and
+                // `>>>` shifts in zeros, as in Java.
+                if chars.peek() == Some(&'>') {
+                    chars.next();
+                    Token::Operator(Operator::LogicalShiftRight)
+                } else {
+                    Token::Operator(Operator::ArithmeticShiftRight)
+                }
+            }
+            '^' => Token::Operator(Operator::Power),
+            '(' => {
+                // Juxtaposition multiplies, as it does on the keypad.
+                if ends_value(tokens.last()) {
+                    tokens.push(Token::Operator(Operator::Multiplication));
+                }
+                Token::LeftParenthesis
+            }
+            ')' => Token::RightParenthesis,
+            ',' => Token::Comma,
+            '=' => Token::Equals,
+            _ => return Err(EvalError::InvalidCharacter(c)),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+// Words are operator keywords, function names, angle unit suffixes, numbers
+// spelled with letter digits only in bases above ten (such as `FF`),
+// constants or else variable names. In the large bases a number that reads
+// as a keyword, like `AND` in base 36, takes a leading zero.
+fn word_token(word: &str, base: u8) -> Result<Token, EvalError> {
+    match word.to_ascii_lowercase().as_str() {
+        "mod" => return Ok(Token::Operator(Operator::Modulo)),
+        "rem" => return Ok(Token::Operator(Operator::Remainder)),
+        "div" => return Ok(Token::Operator(Operator::EuclideanDivision)),
+        "and" => return Ok(Token::Operator(Operator::And)),
+        "or" => return Ok(Token::Operator(Operator::Or)),
+        "xor" => return Ok(Token::Operator(Operator::Xor)),
+        "nand" => return Ok(Token::Operator(Operator::Nand)),
+        "nor" => return Ok(Token::Operator(Operator::Nor)),
+        "not" => return Ok(Token::Not),
+        "shl" => return Ok(Token::Operator(Operator::ShiftLeft)),
+        "shr" => return Ok(Token::Operator(Operator::LogicalShiftRight)),
+        "sar" => return Ok(Token::Operator(Operator::ArithmeticShiftRight)),
+        "rol" => return Ok(Token::Operator(Operator::RotateLeft)),
+        "ror" => return Ok(Token::Operator(Operator::RotateRight)),
+        name => {
+            if let Some(function) = Function::from_name(name) {
+                return Ok(Token::Function(function));
+            }
+            if let Some(unit) = AngleUnit::from_suffix(name) {
+                return Ok(Token::Angle(unit));
+            }
+        }
+    }
+
+    // Letter digits come before constants, so that `e` and `c` are still
+    // digits in hex.
+    if word.chars().all(|c| c.is_digit(base as u32)) {
+        return number_token(word, base);
+    }
+    Ok(match constants::lookup(word) {
+        Some(constant) => Token::Constant(constant),
+        None => Token::Identifier(word.to_string()),
+    })
+}
+
+// `0x`, `0o` and `0b` switch a literal to hex, octal or binary, unless the
+// letter is itself a digit in the current base (`0b1` is a hex number).
+fn radix_prefix(chars: &Peekable<Chars>, base: u8) -> Option<u8> {
+    let mut lookahead = chars.clone();
+    if lookahead.next() != Some('0') {
+        return None;
+    }
+    let letter = lookahead.next()?;
+    let radix = match letter.to_ascii_lowercase() {
+        'x' => 16,
+        'o' => 8,
+        'b' => 2,
+        _ => return None,
+    };
+    let digit = lookahead.next()?;
+    (!letter.is_digit(base as u32) && digit.is_digit(radix)).then_some(radix as u8)
+}
+
+// `%` is a percentage when nothing that could be its right operand comes
+// next, as in `10%` or `200 * 10% / 4`, and the remainder in `7 % 3`. A sign
+// starts an operand, so `7 % -3` is a remainder too.
+fn percent_follows(chars: &Peekable<Chars>, base: u8) -> bool {
+    let mut lookahead = chars.clone().skip_while(|c| c.is_whitespace()).peekable();
+    match lookahead.peek() {
+        Some(&c) if c.is_alphanumeric() || c == '_' => {
+            let word: String = lookahead.take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+            matches!(word_token(&word, base), Ok(Token::Operator(_)))
+        }
+        Some('(' | '.' | '~' | '+' | '-') => false,
+        _ => true,
+    }
+}
+
+fn ends_value(token: Option<&Token>) -> bool {
+    matches!(
+        token,
+        Some(Token::Number(_) | Token::RightParenthesis | Token::Constant(_))
+    )
+}
+
+fn number_token(digits: &str, base: u8) -> Result<Token, EvalError> {
+    Integer::parse_bytes(digits.as_bytes(), base as u32)
+        .map(|n| Token::Number(Value::from(n)))
+        .ok_or(EvalError::UnexpectedToken)
+}
+
+fn take_digits(chars: &mut Peekable<Chars>, base: u8) -> String {
+    let mut digits = String::new();
+    while let Some(&c) = chars.peek() {
+        if !c.is_digit(base as u32) {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+// A bracketed run of digits right after a fraction is its repeating block.
+// Brackets rather than parentheses, which multiply: 2.5(3) is 7.5.
+fn take_repeating(chars: &mut Peekable<Chars>) -> Result<String, EvalError> {
+    if chars.peek() != Some(&'[') {
+        return Ok(String::new());
+    }
+    chars.next();
+    let digits = take_digits(chars, 10);
+    if digits.is_empty() || chars.next() != Some(']') {
+        return Err(EvalError::UnexpectedToken);
+    }
+    Ok(digits)
+}
+
+// `e` or `E` and a power of ten, possibly signed, right after a decimal
+// number, as results are displayed. Without digits to follow, the `e` is
+// left alone to be the constant: `2e` is 2 times e.
+fn take_exponent(chars: &mut Peekable<Chars>) -> Result<Option<i64>, EvalError> {
+    let mut lookahead = chars.clone();
+    if !matches!(lookahead.next(), Some('e' | 'E')) {
+        return Ok(None);
+    }
+    let negative = match lookahead.peek() {
+        Some(&sign @ ('+' | '-')) => {
+            lookahead.next();
+            sign == '-'
+        }
+        _ => false,
+    };
+    let digits = take_digits(&mut lookahead, 10);
+    if digits.is_empty() {
+        return Ok(None);
+    }
+    *chars = lookahead;
+    let exponent: i64 = digits.parse().map_err(|_| EvalError::DigitLimit)?;
+    Ok(Some(if negative { -exponent } else { exponent }))
+}
+
+// whole.fraction(repeating) is (whole.fraction) plus the repeating block over
+// as many nines as it has digits, shifted past the fraction.
+fn decimal_token(whole: &str, fraction: &str, repeating: &str) -> Result<Token, EvalError> {
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(EvalError::UnexpectedToken);
+    }
+    let ten = Integer::from(10);
+    let digits = Integer::parse_bytes(format!("0{}{}", whole, fraction).as_bytes(), 10)
+        .ok_or(EvalError::UnexpectedToken)?;
+    let scale = ten.pow(fraction.len() as u32);
+    let mut value = Value::new(digits, scale.clone());
+    if !repeating.is_empty() {
+        let block = Integer::parse_bytes(repeating.as_bytes(), 10).ok_or(EvalError::UnexpectedToken)?;
+        let nines = ten.pow(repeating.len() as u32) - 1;
+        value = value + Value::new(block, scale * nines);
+    }
+    Ok(Token::Number(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(input: &str, base: u8) -> Result<String, EvalError> {
+        let tokens = tokenize(input, base, 10_000)?;
+        Ok(tokens.iter().map(Token::to_string).collect::<Vec<_>>().join(" "))
+    }
+
+    #[test]
+    fn reads_numbers_in_the_base() {
+        assert_eq!(lex("255", 10), Ok("255".to_string()));
+        assert_eq!(lex("FF", 16), Ok("255".to_string()));
+        assert_eq!(lex("0x1f + 0b11 + 0o7", 10), Ok("31 + 3 + 7".to_string()));
+        // `b` is a hex digit, so this is one hex number.
+        assert_eq!(lex("0b1", 16), Ok("177".to_string()));
+        // Numbers come out as unsigned bit patterns for the word to read.
+        assert_eq!(lex("FFFFFFFFFFFFFFFF", 16), Ok("18446744073709551615".to_string()));
+        let big = "340282366920938463463374607431768211456";
+        assert_eq!(lex(big, 10), Ok(big.to_string()));
+        assert_eq!(lex("0.1[6]", 10), Ok("1/6".to_string()));
+        assert_eq!(lex(".5", 10), Ok("1/2".to_string()));
+    }
+
+    #[test]
+    fn reads_scientific_notation() {
+        assert_eq!(lex("1e-7", 10), Ok("1/10000000".to_string()));
+        assert_eq!(lex("1.5e3", 10), Ok("1500".to_string()));
+        assert_eq!(lex("2E+2", 10), Ok("200".to_string()));
+        assert_eq!(lex("2.5e-1x", 10), Ok("1/4 * x".to_string()));
+        // Without digits after it, `e` is the constant.
+        assert_eq!(lex("2e", 10), Ok("2 * e".to_string()));
+        assert_eq!(lex("2e-x", 10), Ok("2 * e - x".to_string()));
+        assert_eq!(lex("2exp(1)", 10), Ok("2 * exp ( 1 )".to_string()));
+        // In hex, `e` is a digit.
+        assert_eq!(lex("1e3", 16), Ok("483".to_string()));
+        assert_eq!(tokenize("1e10001", 10, 10_000).err(), Some(EvalError::DigitLimit));
+        assert_eq!(tokenize("1e99999999999999999999", 10, 10_000).err(), Some(EvalError::DigitLimit));
+    }
+
+    #[test]
+    fn juxtaposition_multiplies() {
+        assert_eq!(lex("2pi", 10), Ok("2 * pi".to_string()));
+        assert_eq!(lex("3(4)", 10), Ok("3 * ( 4 )".to_string()));
+        assert_eq!(lex("2.5(3)", 10), Ok("5/2 * ( 3 )".to_string()));
+        assert_eq!(lex("(1)(2)", 10), Ok("( 1 ) * ( 2 )".to_string()));
+        assert_eq!(lex("4x", 10), Ok("4 * x".to_string()));
+    }
+
+    #[test]
+    fn reads_operators() {
+        assert_eq!(lex("1 ** 2 ^ 3", 10), Ok("1 ^ 2 ^ 3".to_string()));
+        assert_eq!(lex("1×2÷3", 10), Ok("1 * 2 / 3".to_string()));
+        assert_eq!(lex("1 << 2 >> 3 >>> 4", 10), Ok("1 SHL 2 SAR 3 SHR 4".to_string()));
+        assert_eq!(lex("1 rol 2 ROR 3", 10), Ok("1 ROL 2 ROR 3".to_string()));
+        assert_eq!(lex("7 mod 3 div 2 rem 1", 10), Ok("7 mod 3 div 2 rem 1".to_string()));
+        assert_eq!(lex("~1 nand 2 NOR 3 & 4 | 5", 10), Ok("NOT 1 NAND 2 NOR 3 AND 4 OR 5".to_string()));
+        assert_eq!(lex("30deg", 10), Ok("30 deg".to_string()));
+    }
+
+    #[test]
+    fn tells_percent_from_remainder() {
+        assert_eq!(lex("10%", 10), Ok("10 %".to_string()));
+        assert_eq!(lex("200 + 10%", 10), Ok("200 + 10 %".to_string()));
+        assert_eq!(lex("10% * 3", 10), Ok("10 % * 3".to_string()));
+        assert_eq!(lex("10% mod 3", 10), Ok("10 % mod 3".to_string()));
+        assert_eq!(lex("(10%)", 10), Ok("( 10 % )".to_string()));
+        assert_eq!(lex("7 % 3", 10), Ok("7 rem 3".to_string()));
+        assert_eq!(lex("7 % x", 10), Ok("7 rem x".to_string()));
+        assert_eq!(lex("7 % (3)", 10), Ok("7 rem ( 3 )".to_string()));
+        assert_eq!(lex("7 % -3", 10), Ok("7 rem - 3".to_string()));
+        assert_eq!(lex("7 % +3", 10), Ok("7 rem + 3".to_string()));
+    }
+
+    #[test]
+    fn rejects_unknown_characters() {
+        assert_eq!(lex("1 $ 2", 10), Err(EvalError::InvalidCharacter('$')));
+    }
+}
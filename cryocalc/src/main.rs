@@ -1,74 +1,191 @@
 // main.rs
 use iced::{
-    widget::{button, column, row, text_input, Button, Text},
-    Background, Border, Color, Element, Length, Renderer, Shadow, Theme,
+    clipboard, event, keyboard,
+    widget::{button, column, row, Text},
+    Element, Event, Length, Subscription, Task, Theme,
 };
 mod calculator;
+mod cli;
+mod complex_number;
+mod constants;
+mod error;
+mod functions;
+mod lexer;
+mod panels;
+mod parser;
+mod rational;
 mod types;
 
-// use calculator::Calculator;
-use types::{CalculatorMode};
+use calculator::Calculator;
+use types::{AngleUnit, CalculatorMode};
 
 mod binary;
+mod complex;
 mod hex;
+mod oct;
+mod radix;
 mod dec;
+mod scientific;
 mod theme;
 
 //#[derive(Default)]
 struct CryoCalc {
     dec_state: dec::DecCalcState,
+    sci_state: scientific::SciCalcState,
+    complex_state: complex::ComplexCalcState,
     bin_state: binary::BinCalcState,
+    oct_state: oct::OctCalcState,
     hex_state: hex::HexCalcState,
+    radix_state: radix::RadixCalcState,
     current_mode: CalculatorMode,
+    angle_unit: AngleUnit,
+    // Whether switching modes takes the expression along, rather than
+    // each mode keeping a session of its own.
+    carry_expression: bool,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
-    DecMessage(dec::Message),
-    BinMessage(binary::Message),
-    HexMessage(hex::Message),
+    Dec(dec::Message),
+    Sci(scientific::Message),
+    Complex(complex::Message),
+    Bin(binary::Message),
+    Oct(oct::Message),
+    Hex(hex::Message),
+    Radix(radix::Message),
     CycleMode,
+    CycleAngleUnit,
+    ToggleCarryExpression,
 }
 
 impl Default for CryoCalc {
     fn default() -> Self {
         Self {
             dec_state: dec::DecCalcState::new(),
+            sci_state: scientific::SciCalcState::new(),
+            complex_state: complex::ComplexCalcState::new(),
             bin_state: binary::BinCalcState::new(),
+            oct_state: oct::OctCalcState::new(),
             hex_state: hex::HexCalcState::new(),
+            radix_state: radix::RadixCalcState::new(),
             current_mode: CalculatorMode::Decimal,
+            angle_unit: AngleUnit::default(),
+            carry_expression: true,
         }
     }
 }
 
 pub fn main() -> iced::Result {
+    // With an expression on the command line, print its value and exit
+    // instead of opening the window.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     iced::application("CryoCalc", CryoCalc::update, CryoCalc::view)
+        .subscription(CryoCalc::subscription)
         .theme(|_| Theme::Oxocarbon)
         .centered()
         .run()
 }
 
 impl CryoCalc {
-    fn update(&mut self, message: Message) {
+    fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::DecMessage(msg) => self.dec_state.update(msg),
-            Message::BinMessage(msg) => self.bin_state.update(msg),
-            Message::HexMessage(msg) => self.hex_state.update(msg),
+            Message::Dec(dec::Message::Readout(readout))
+            | Message::Sci(scientific::Message::Readout(readout))
+            | Message::Bin(binary::Message::Readout(readout))
+            | Message::Oct(oct::Message::Readout(readout))
+            | Message::Hex(hex::Message::Readout(readout))
+            | Message::Radix(radix::Message::Readout(readout)) => match readout {
+                panels::Readout::Copy(digits) => return clipboard::write(digits),
+                panels::Readout::Switch(mode) => self.switch_mode(mode),
+            },
+            Message::Dec(msg) => self.dec_state.update(msg),
+            Message::Sci(msg) => self.sci_state.update(msg),
+            Message::Complex(msg) => self.complex_state.update(msg),
+            Message::Bin(msg) => self.bin_state.update(msg),
+            Message::Oct(msg) => self.oct_state.update(msg),
+            Message::Hex(msg) => self.hex_state.update(msg),
+            Message::Radix(msg) => self.radix_state.update(msg),
             Message::CycleMode => {
-                self.current_mode = match self.current_mode {
-                    CalculatorMode::Decimal => CalculatorMode::Binary,
-                    CalculatorMode::Binary => CalculatorMode::Hex,
-                    CalculatorMode::Hex => CalculatorMode::Decimal,
-                }
+                let mode = match self.current_mode {
+                    CalculatorMode::Decimal => CalculatorMode::Scientific,
+                    CalculatorMode::Scientific => CalculatorMode::Complex,
+                    CalculatorMode::Complex => CalculatorMode::Binary,
+                    CalculatorMode::Binary => CalculatorMode::Octal,
+                    CalculatorMode::Octal => CalculatorMode::Hex,
+                    CalculatorMode::Hex => CalculatorMode::Radix,
+                    CalculatorMode::Radix => CalculatorMode::Decimal,
+                };
+                self.switch_mode(mode);
+            }
+            Message::ToggleCarryExpression => self.carry_expression = !self.carry_expression,
+            Message::CycleAngleUnit => {
+                // The angle unit is shared by every mode.
+                self.angle_unit = self.angle_unit.next();
+                self.dec_state.set_angle_unit(self.angle_unit);
+                self.sci_state.set_angle_unit(self.angle_unit);
+                self.complex_state.set_angle_unit(self.angle_unit);
+                self.bin_state.set_angle_unit(self.angle_unit);
+                self.oct_state.set_angle_unit(self.angle_unit);
+                self.hex_state.set_angle_unit(self.angle_unit);
+                self.radix_state.set_angle_unit(self.angle_unit);
+            }
+        }
+        Task::none()
+    }
+
+    // Numbers carried into another mode keep their values, so 255 typed in
+    // decimal shows as FF in hex.
+    fn switch_mode(&mut self, mode: CalculatorMode) {
+        if self.carry_expression && mode != self.current_mode {
+            let expression = match self.current_mode {
+                CalculatorMode::Decimal => self.dec_state.expression(),
+                CalculatorMode::Scientific => self.sci_state.expression(),
+                CalculatorMode::Complex => self.complex_state.expression(),
+                CalculatorMode::Binary => self.bin_state.expression(),
+                CalculatorMode::Octal => self.oct_state.expression(),
+                CalculatorMode::Hex => self.hex_state.expression(),
+                CalculatorMode::Radix => self.radix_state.expression(),
+            };
+            match mode {
+                CalculatorMode::Decimal => self.dec_state.set_expression(expression),
+                CalculatorMode::Scientific => self.sci_state.set_expression(expression),
+                CalculatorMode::Complex => self.complex_state.set_expression(expression),
+                CalculatorMode::Binary => self.bin_state.set_expression(expression),
+                CalculatorMode::Octal => self.oct_state.set_expression(expression),
+                CalculatorMode::Hex => self.hex_state.set_expression(expression),
+                CalculatorMode::Radix => self.radix_state.set_expression(expression),
             }
         }
+        self.current_mode = mode;
     }
 
-    fn view(&self) -> Element<Message> {
+    // F2 cycles the angle unit, even while the display has focus.
+    fn subscription(&self) -> Subscription<Message> {
+        event::listen_with(|event, _status, _window| match event {
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(keyboard::key::Named::F2),
+                ..
+            }) => Some(Message::CycleAngleUnit),
+            _ => None,
+        })
+    }
+
+    fn view(&self) -> Element<'_, Message> {
         let mode_text = match self.current_mode {
-            CalculatorMode::Decimal => "DEC",
-            CalculatorMode::Binary => "BIN",
-            CalculatorMode::Hex => "HEX",
+            CalculatorMode::Decimal => format!("DEC · {}", self.angle_unit),
+            CalculatorMode::Scientific => format!("SCI · {}", self.angle_unit),
+            CalculatorMode::Complex => format!("CPLX · {}", self.angle_unit),
+            CalculatorMode::Binary => word_label("BIN", self.bin_state.calculator()),
+            CalculatorMode::Octal => word_label("OCT", self.oct_state.calculator()),
+            CalculatorMode::Hex => word_label("HEX", self.hex_state.calculator()),
+            CalculatorMode::Radix => {
+                let calculator = self.radix_state.calculator();
+                word_label(&format!("BASE {}", calculator.base()), calculator)
+            }
         };
         
         let mode_button = button(Text::new(format!("Switch Mode ({})", mode_text)))
@@ -77,13 +194,25 @@ impl CryoCalc {
             .padding(10);
 
         let current_view = match self.current_mode {
-            CalculatorMode::Decimal => self.dec_state.view().map(Message::DecMessage),
-            CalculatorMode::Binary => self.bin_state.view().map(Message::BinMessage),
-            CalculatorMode::Hex => self.hex_state.view().map(Message::HexMessage),
+            CalculatorMode::Decimal => self.dec_state.view().map(Message::Dec),
+            CalculatorMode::Scientific => self.sci_state.view().map(Message::Sci),
+            CalculatorMode::Complex => self.complex_state.view().map(Message::Complex),
+            CalculatorMode::Binary => self.bin_state.view().map(Message::Bin),
+            CalculatorMode::Octal => self.oct_state.view().map(Message::Oct),
+            CalculatorMode::Hex => self.hex_state.view().map(Message::Hex),
+            CalculatorMode::Radix => self.radix_state.view().map(Message::Radix),
         };
 
+        let carry_button = button(Text::new(if self.carry_expression {
+            "Carry expression"
+        } else {
+            "Separate sessions"
+        }))
+        .on_press(Message::ToggleCarryExpression)
+        .padding(10);
+
         column![
-            mode_button,
+            row![mode_button, carry_button].spacing(10),
             current_view
         ]
         .spacing(10)
@@ -91,3 +220,13 @@ impl CryoCalc {
         .into()
     }
 }
+
+fn word_label(mode: &str, calculator: &Calculator) -> String {
+    let word_size = calculator.word_size();
+    format!(
+        "{} · {} {}",
+        mode,
+        word_size,
+        word_size.type_name(calculator.is_signed())
+    )
+}
@@ -0,0 +1,213 @@
+use crate::{calculator::Calculator, types::AngleUnit, types::Token, types::Operator, types::WordSize};
+
+use crate::error::EvalError;
+use crate::panels;
+
+use iced::{
+    widget::{button, column, row, text_input, Text},
+    Element, Length,
+};
+
+#[derive(Default)]
+pub struct OctCalcState {
+    calculator: Calculator,
+    display_content: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ButtonPressed(Token),
+    DisplayContentChanged(String),
+    DisplayContentSubmitted,
+    CycleOverflowPolicy,
+    CycleWordSize,
+    ToggleSigned,
+    Readout(panels::Readout),
+}
+
+impl OctCalcState {
+    fn button<'a>(token: Token) -> Element<'a, Message> {
+        button(Text::new(token.to_string()))
+            .on_press(Message::ButtonPressed(token))
+            .width(Length::Fill)
+            .padding(16)
+            .into()
+    }
+
+    pub fn new() -> Self {
+        OctCalcState {
+            calculator: Calculator::new()
+                .with_base(8)
+                .with_word_size(WordSize::Qword),
+            display_content: String::new(),
+        }
+    }
+
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.calculator.set_angle_unit(unit);
+    }
+
+    pub fn expression(&self) -> Result<Vec<Token>, EvalError> {
+        self.calculator.expression()
+    }
+
+    pub fn set_expression(&mut self, expression: Result<Vec<Token>, EvalError>) {
+        self.display_content = match expression.and_then(|e| self.calculator.set_expression(e)) {
+            Ok(()) => self.calculator.get_display(),
+            Err(error) => error.to_string(),
+        };
+    }
+
+    pub fn calculator(&self) -> &Calculator {
+        &self.calculator
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::ButtonPressed(token) => {
+                if token.is_valid_for_base(8) {
+                    self.calculator.add_token(token.clone());
+                    if let Token::Equals = token {
+                        self.display_content = match self.calculator.evaluate() {
+                            Ok(result) => self.calculator.format_number(&result),
+                            Err(error) => error.to_string(),
+                        };
+                    } else {
+                        self.display_content = self.calculator.get_display();
+                    }
+                }
+            }
+            Message::DisplayContentChanged(content) => self.display_content = content,
+            Message::DisplayContentSubmitted => {
+                match self.calculator.evaluate_input(&self.display_content) {
+                    Ok(Some(result)) => self.display_content = self.calculator.format_number(&result),
+                    // A function definition stays in the display as typed.
+                    Ok(None) => {}
+                    Err(error) => self.display_content = error.to_string(),
+                }
+            }
+            Message::CycleOverflowPolicy => {
+                let policy = self.calculator.overflow_policy().next();
+                self.calculator.set_overflow_policy(policy);
+            }
+            Message::CycleWordSize => {
+                let word_size = self.calculator.word_size().next();
+                self.calculator.set_word_size(word_size);
+                self.display_content = self.calculator.get_display();
+            }
+            Message::ToggleSigned => {
+                let signed = !self.calculator.is_signed();
+                self.calculator.set_signed(signed);
+                self.display_content = self.calculator.get_display();
+            }
+            // Copying and switching modes are up to the application.
+            Message::Readout(_) => {}
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let space = 5;
+        row![
+            column![
+                column![
+                    text_input("...", &self.display_content)
+                        .padding(space)
+                        .on_input(Message::DisplayContentChanged)
+                        .on_submit(Message::DisplayContentSubmitted),
+                    row![
+                        button(Text::new(format!(
+                            "Overflow: {}",
+                            self.calculator.overflow_policy()
+                        )))
+                        .on_press(Message::CycleOverflowPolicy)
+                        .padding(space),
+                        button(Text::new(self.calculator.word_size().to_string()))
+                            .on_press(Message::CycleWordSize)
+                            .padding(space),
+                        button(Text::new(if self.calculator.is_signed() {
+                            "Signed"
+                        } else {
+                            "Unsigned"
+                        }))
+                        .on_press(Message::ToggleSigned)
+                        .padding(space),
+                    ]
+                    .spacing(space),
+                    panels::readout(&self.calculator, Message::Readout),
+                ]
+                .spacing(space)
+                .padding(15),
+                column![
+                    row![
+                        OctCalcState::button(Token::ClearScreen),
+                        OctCalcState::button(Token::LeftParenthesis),
+                        OctCalcState::button(Token::RightParenthesis),
+                        OctCalcState::button(Token::Operator(Operator::Division)),
+                        OctCalcState::button(Token::ClearToken),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        OctCalcState::button(Token::Operator(Operator::Remainder)),
+                        OctCalcState::button(Token::Operator(Operator::Modulo)),
+                        OctCalcState::button(Token::Operator(Operator::EuclideanDivision)),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        OctCalcState::button(Token::Operator(Operator::And)),
+                        OctCalcState::button(Token::Operator(Operator::Or)),
+                        OctCalcState::button(Token::Operator(Operator::Xor)),
+                        OctCalcState::button(Token::Not),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        OctCalcState::button(Token::Operator(Operator::Nand)),
+                        OctCalcState::button(Token::Operator(Operator::Nor)),
+                        OctCalcState::button(Token::Operator(Operator::RotateLeft)),
+                        OctCalcState::button(Token::Operator(Operator::RotateRight)),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        OctCalcState::button(Token::Operator(Operator::ShiftLeft)),
+                        OctCalcState::button(Token::Operator(Operator::LogicalShiftRight)),
+                        OctCalcState::button(Token::Operator(Operator::ArithmeticShiftRight)),
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        OctCalcState::button(Token::Digit(4)),
+                        OctCalcState::button(Token::Digit(5)),
+                        OctCalcState::button(Token::Digit(6)),
+                        OctCalcState::button(Token::Digit(7)),
+                        OctCalcState::button(Token::Operator(Operator::Multiplication))
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        OctCalcState::button(Token::Digit(0)),
+                        OctCalcState::button(Token::Digit(1)),
+                        OctCalcState::button(Token::Digit(2)),
+                        OctCalcState::button(Token::Digit(3)),
+                        OctCalcState::button(Token::Operator(Operator::Subtraction))
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                    row![
+                        OctCalcState::button(Token::ToggleSign),
+                        OctCalcState::button(Token::Operator(Operator::Addition)),
+                        OctCalcState::button(Token::Operator(Operator::Power)),
+                        OctCalcState::button(Token::Equals)
+                    ]
+                    .spacing(space * 2)
+                    .padding(space),
+                ]
+                .padding(10)
+            ],
+            panels::variables(&self.calculator, Message::ButtonPressed),
+        ]
+        .into()
+    }
+}
\ No newline at end of file
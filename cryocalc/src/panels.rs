@@ -0,0 +1,134 @@
+use iced::{
+    widget::{button, column, row, scrollable, Column, Text},
+    Element, Length,
+};
+
+use crate::calculator::Calculator;
+use crate::types::{CalculatorMode, Token};
+
+/// Lists the calculator's variables with their values in its own base, then
+/// its user functions as they were typed. Pressing one enters its name into
+/// the expression.
+pub fn variables<'a, Message: Clone + 'a>(
+    calculator: &Calculator,
+    on_press: fn(Token) -> Message,
+) -> Element<'a, Message> {
+    let variables = calculator
+        .variables()
+        .iter()
+        .map(|(name, value)| (name, format!("{} = {}", name, calculator.format_number(value))));
+    panel(calculator, variables.collect(), on_press)
+}
+
+/// The same for complex mode, which keeps values of its own.
+pub fn complex_variables<'a, Message: Clone + 'a>(
+    calculator: &Calculator,
+    on_press: fn(Token) -> Message,
+) -> Element<'a, Message> {
+    let variables = calculator
+        .complex_variables()
+        .iter()
+        .map(|(name, value)| (name, format!("{} = {}", name, calculator.format_complex(value))));
+    panel(calculator, variables.collect(), on_press)
+}
+
+fn panel<'a, Message: Clone + 'a>(
+    calculator: &Calculator,
+    variables: Vec<(&String, String)>,
+    on_press: fn(Token) -> Message,
+) -> Element<'a, Message> {
+    let hint = if variables.is_empty() && calculator.user_functions().is_empty() {
+        "Type name = expression to keep a value, or f(x) = expression for a function"
+    } else {
+        ""
+    };
+    let functions = calculator
+        .user_functions()
+        .iter()
+        .map(|(name, function)| (name, function.text.clone()));
+    let entries = variables.into_iter().chain(functions).map(|(name, text)| {
+        button(Text::new(text))
+            .on_press(on_press(Token::Identifier(name.clone())))
+            .width(Length::Fill)
+            .padding(5)
+            .into()
+    });
+    column![
+        Text::new("Variables"),
+        Text::new(hint).size(12),
+        scrollable(Column::with_children(entries).spacing(5)),
+    ]
+    .spacing(5)
+    .padding(15)
+    .width(220)
+    .into()
+}
+
+/// What pressing a line of the base readout asks for: a copy of the digits,
+/// or the mode that works in that base.
+#[derive(Debug, Clone)]
+pub enum Readout {
+    Copy(String),
+    Switch(CalculatorMode),
+}
+
+/// Shows the number on the display in decimal, hex, octal and binary, with
+/// digits grouped, then as signed and unsigned when there is a word.
+/// Pressing a base switches to its mode; pressing the digits copies them
+/// ungrouped, as the calculator would read them back.
+pub fn readout<'a, Message: Clone + 'a>(
+    calculator: &Calculator,
+    on_press: fn(Readout) -> Message,
+) -> Element<'a, Message> {
+    let Some(n) = calculator.shown_value().filter(|n| n.is_integer()).map(|n| n.to_integer()) else {
+        return column![].into();
+    };
+    let line = |label: String, mode: Option<CalculatorMode>, digits: String, group: usize| {
+        let label = button(Text::new(label).size(12))
+            .on_press_maybe(mode.map(|mode| on_press(Readout::Switch(mode))))
+            .style(button::text)
+            .width(60)
+            .padding(2);
+        let value = button(Text::new(grouped(&digits, group)).size(12))
+            .on_press(on_press(Readout::Copy(digits)))
+            .style(button::text)
+            .width(Length::Fill)
+            .padding(2);
+        row![label, value].into()
+    };
+
+    let mut lines: Vec<Element<'a, Message>> = vec![
+        line("HEX".to_string(), Some(CalculatorMode::Hex), calculator.format_in_base(&n, 16), 4),
+        line("DEC".to_string(), Some(CalculatorMode::Decimal), n.to_string(), 3),
+        line("OCT".to_string(), Some(CalculatorMode::Octal), calculator.format_in_base(&n, 8), 3),
+        line("BIN".to_string(), Some(CalculatorMode::Binary), calculator.format_in_base(&n, 2), 4),
+    ];
+    let word_size = calculator.word_size();
+    if word_size.bits().is_some() {
+        for signed in [true, false] {
+            let value = word_size.wrap(&n, signed);
+            lines.push(line(word_size.type_name(signed), None, value.to_string(), 3));
+        }
+    }
+    Column::with_children(lines).into()
+}
+
+// Splits digits into groups counted from the right, keeping any sign in
+// front.
+fn grouped(digits: &str, group: usize) -> String {
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", digits),
+    };
+    let first = match digits.len() % group {
+        0 => group,
+        first => first,
+    };
+    let (head, tail) = digits.split_at(first.min(digits.len()));
+    let mut text = format!("{}{}", sign, head);
+    for chunk in tail.as_bytes().chunks(group) {
+        text.push(' ');
+        text.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+    }
+    text
+}
@@ -0,0 +1,354 @@
+// parser.rs
+use std::collections::BTreeSet;
+
+use crate::constants::Constant;
+use crate::error::EvalError;
+use crate::types::{AngleUnit, Function, Operator, Token, UnaryOperator, Value};
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Number(Value),
+    Unary(UnaryOperator, Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+    Constant(&'static Constant),
+    Variable(String),
+    UserCall(String, Vec<Expr>),
+    Angle(AngleUnit, Box<Expr>),
+    Percent(Box<Expr>),
+}
+
+/// What a line of input asks for: a value, a value to keep under a name,
+/// or a function to keep for later.
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Expression(Expr),
+    Assignment(String, Expr),
+    Definition(String, Vec<String>, Expr),
+}
+
+/// How deeply an expression may nest, counting both open parentheses and
+/// the operations stacked on one another, before parsing gives up rather
+/// than run out of stack while evaluating it.
+const MAX_NESTING: usize = 256;
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    depth: usize,
+}
+
+/// Splits off a leading `name =` or `name(parameters) =` and parses the
+/// rest as an expression.
+pub fn parse(tokens: &[Token]) -> Result<Statement, EvalError> {
+    if let Some((name, parameters, body)) = definition(tokens) {
+        let unique: BTreeSet<_> = parameters.iter().collect();
+        if unique.len() < parameters.len() {
+            return Err(EvalError::UnexpectedToken);
+        }
+        return Ok(Statement::Definition(name, parameters, parse_expression(body)?));
+    }
+    match tokens {
+        [Token::Identifier(name), Token::Equals, rest @ ..] => {
+            Ok(Statement::Assignment(name.clone(), parse_expression(rest)?))
+        }
+        // Built-in names cannot be redefined, as values or as functions.
+        [Token::Constant(constant), ..] if tokens.iter().any(|t| matches!(t, Token::Equals)) => {
+            Err(EvalError::ReadOnly(constant.name.to_string()))
+        }
+        [Token::Function(function), ..] if tokens.iter().any(|t| matches!(t, Token::Equals)) => {
+            Err(EvalError::ReadOnly(function.name().to_string()))
+        }
+        _ => parse_expression(tokens).map(Statement::Expression),
+    }
+}
+
+// The name, parameters and body of `name(a, b, ...) = body`.
+fn definition(tokens: &[Token]) -> Option<(String, Vec<String>, &[Token])> {
+    let [Token::Identifier(name), Token::LeftParenthesis, rest @ ..] = tokens else {
+        return None;
+    };
+    let mut rest = rest;
+    let mut parameters = Vec::new();
+    loop {
+        match rest {
+            [Token::RightParenthesis, Token::Equals, body @ ..] if parameters.is_empty() => {
+                return Some((name.clone(), parameters, body));
+            }
+            [Token::Identifier(parameter), Token::Comma, tail @ ..] => {
+                parameters.push(parameter.clone());
+                rest = tail;
+            }
+            [Token::Identifier(parameter), Token::RightParenthesis, Token::Equals, body @ ..] => {
+                parameters.push(parameter.clone());
+                return Some((name.clone(), parameters, body));
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Turns a token stream into an expression tree, honouring operator
+/// precedence and associativity.
+fn parse_expression(tokens: &[Token]) -> Result<Expr, EvalError> {
+    if tokens.is_empty() {
+        return Err(EvalError::EmptyExpression);
+    }
+
+    let mut parser = Parser { tokens, pos: 0, depth: 0 };
+    let (expr, _) = parser.expression(0)?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(Token::RightParenthesis) => Err(EvalError::UnbalancedParentheses),
+        Some(_) => Err(EvalError::UnexpectedToken),
+    }
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    // Precedence climbing: keep folding operators into `lhs` for as long as
+    // they bind at least as tightly as `min_precedence`. Returns the tree
+    // with its height, so that a long chain of operations counts as deeply
+    // as the nesting it evaluates with.
+    fn expression(&mut self, min_precedence: u8) -> Result<(Expr, usize), EvalError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(EvalError::NestingLimit);
+        }
+        let (mut lhs, mut height) = self.operand()?;
+
+        // Unit suffixes and percent signs bind tighter than any operator:
+        // -30deg is -(30deg) and 2^30deg is 2^(30deg).
+        loop {
+            lhs = match self.peek() {
+                Some(Token::Angle(unit)) => Expr::Angle(*unit, Box::new(lhs)),
+                Some(Token::Percent) => Expr::Percent(Box::new(lhs)),
+                _ => break,
+            };
+            self.pos += 1;
+            height = nest(height)?;
+        }
+
+        while let Some(Token::Operator(op)) = self.peek() {
+            let op = *op;
+            let precedence = op.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+            self.pos += 1;
+
+            let next_precedence = if op.is_right_associative() {
+                precedence
+            } else {
+                precedence + 1
+            };
+            let (rhs, rhs_height) = self.expression(next_precedence)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+            height = nest(height.max(rhs_height))?;
+        }
+
+        self.depth -= 1;
+        Ok((lhs, height))
+    }
+
+    fn operand(&mut self) -> Result<(Expr, usize), EvalError> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok((Expr::Number(n.clone()), 0)),
+            Some(Token::Constant(constant)) => Ok((Expr::Constant(constant), 0)),
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                // A name straight before a parenthesis calls a function.
+                if let Some(Token::LeftParenthesis) = self.peek() {
+                    self.pos += 1;
+                    let (arguments, height) = self.arguments()?;
+                    return Ok((Expr::UserCall(name, arguments), nest(height)?));
+                }
+                Ok((Expr::Variable(name), 0))
+            }
+            Some(Token::Operator(Operator::Subtraction)) => {
+                let (operand, height) = self.expression(Operator::PREFIX_PRECEDENCE)?;
+                Ok((Expr::Unary(UnaryOperator::Negation, Box::new(operand)), nest(height)?))
+            }
+            // A plus sign leaves its operand as it is, as in `7 % +3`.
+            Some(Token::Operator(Operator::Addition)) => self.expression(Operator::PREFIX_PRECEDENCE),
+            Some(Token::Not) => {
+                let (operand, height) = self.expression(Operator::PREFIX_PRECEDENCE)?;
+                Ok((Expr::Unary(UnaryOperator::Not, Box::new(operand)), nest(height)?))
+            }
+            Some(Token::LeftParenthesis) => {
+                let expr = self.expression(0)?;
+                match self.next() {
+                    Some(Token::RightParenthesis) => Ok(expr),
+                    None => Err(EvalError::UnbalancedParentheses),
+                    Some(_) => Err(EvalError::UnexpectedToken),
+                }
+            }
+            Some(Token::Function(function)) => {
+                let function = *function;
+                match self.next() {
+                    Some(Token::LeftParenthesis) => {
+                        let (arguments, height) = self.arguments()?;
+                        Ok((Expr::Call(function, arguments), nest(height)?))
+                    }
+                    None => Err(EvalError::TrailingOperator),
+                    Some(_) => Err(EvalError::UnexpectedToken),
+                }
+            }
+            None => Err(EvalError::TrailingOperator),
+            Some(_) => Err(EvalError::UnexpectedToken),
+        }
+    }
+
+    // Comma-separated arguments up to the closing parenthesis, which may
+    // come straight away for a call without any. Returns them with the
+    // height of the tallest.
+    fn arguments(&mut self) -> Result<(Vec<Expr>, usize), EvalError> {
+        let mut arguments = Vec::new();
+        let mut height = 0;
+        if let Some(Token::RightParenthesis) = self.peek() {
+            self.pos += 1;
+            return Ok((arguments, height));
+        }
+        loop {
+            let (argument, argument_height) = self.expression(0)?;
+            arguments.push(argument);
+            height = height.max(argument_height);
+            match self.next() {
+                Some(Token::Comma) => {}
+                Some(Token::RightParenthesis) => return Ok((arguments, height)),
+                None => return Err(EvalError::UnbalancedParentheses),
+                Some(_) => return Err(EvalError::UnexpectedToken),
+            }
+        }
+    }
+}
+
+// The height of a node over a subtree of the given height.
+fn nest(height: usize) -> Result<usize, EvalError> {
+    if height >= MAX_NESTING {
+        return Err(EvalError::NestingLimit);
+    }
+    Ok(height + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+
+    fn statement(input: &str) -> Result<Statement, EvalError> {
+        parse(&lexer::tokenize(input, 10, 10_000)?)
+    }
+
+    // The tree with every operation in parentheses.
+    fn show(expr: &Expr) -> String {
+        match expr {
+            Expr::Number(n) => n.to_string(),
+            Expr::Unary(UnaryOperator::Negation, operand) => format!("(-{})", show(operand)),
+            Expr::Unary(UnaryOperator::Not, operand) => format!("(NOT {})", show(operand)),
+            Expr::Binary(op, lhs, rhs) => format!("({} {:?} {})", show(lhs), op, show(rhs)),
+            Expr::Call(function, arguments) => format!("{:?}({})", function, show_all(arguments)),
+            Expr::Constant(constant) => constant.name.to_string(),
+            Expr::Variable(name) => name.clone(),
+            Expr::UserCall(name, arguments) => format!("{}({})", name, show_all(arguments)),
+            Expr::Angle(unit, operand) => format!("({}{})", show(operand), unit.suffix()),
+            Expr::Percent(operand) => format!("({}%)", show(operand)),
+        }
+    }
+
+    fn show_all(arguments: &[Expr]) -> String {
+        arguments.iter().map(show).collect::<Vec<_>>().join(", ")
+    }
+
+    fn tree(input: &str) -> Result<String, EvalError> {
+        match statement(input)? {
+            Statement::Expression(expr) => Ok(show(&expr)),
+            other => panic!("{} is not an expression: {:?}", input, other),
+        }
+    }
+
+    #[test]
+    fn honours_precedence() {
+        assert_eq!(tree("1 + 2 * 3"), Ok("(1 + (2 * 3))".to_string()));
+        assert_eq!(tree("1 * 2 + 3"), Ok("((1 * 2) + 3)".to_string()));
+        assert_eq!(tree("(1 + 2) * 3"), Ok("((1 + 2) * 3)".to_string()));
+        assert_eq!(tree("2 * 3 ^ 2"), Ok("(2 * (3 ^ 2))".to_string()));
+        assert_eq!(tree("1 | 2 xor 3 & 4"), Ok("(1 OR (2 XOR (3 AND 4)))".to_string()));
+        assert_eq!(tree("1 & 2 + 3"), Ok("(1 AND (2 + 3))".to_string()));
+        assert_eq!(tree("1 << 2 + 3"), Ok("(1 SHL (2 + 3))".to_string()));
+        assert_eq!(tree("7 mod 2 * 3"), Ok("((7 mod 2) * 3)".to_string()));
+    }
+
+    #[test]
+    fn honours_associativity() {
+        assert_eq!(tree("10 - 4 - 3"), Ok("((10 - 4) - 3)".to_string()));
+        assert_eq!(tree("64 / 4 / 2"), Ok("((64 / 4) / 2)".to_string()));
+        assert_eq!(tree("2 ^ 3 ^ 2"), Ok("(2 ^ (3 ^ 2))".to_string()));
+    }
+
+    #[test]
+    fn binds_prefix_and_postfix_operators() {
+        // Unary minus binds looser than a power and tighter than a product.
+        assert_eq!(tree("-2 ^ 2"), Ok("(-(2 ^ 2))".to_string()));
+        assert_eq!(tree("-2 * 3"), Ok("((-2) * 3)".to_string()));
+        assert_eq!(tree("2 ^ -1"), Ok("(2 ^ (-1))".to_string()));
+        assert_eq!(tree("--2"), Ok("(-(-2))".to_string()));
+        assert_eq!(tree("+2"), Ok("2".to_string()));
+        assert_eq!(tree("~1 & 2"), Ok("((NOT 1) AND 2)".to_string()));
+        assert_eq!(tree("-30deg"), Ok("(-(30deg))".to_string()));
+        assert_eq!(tree("200 + 10%"), Ok("(200 + (10%))".to_string()));
+    }
+
+    #[test]
+    fn parses_calls() {
+        assert_eq!(tree("max(1, 2 + 3, 4)"), Ok("max(1, (2 + 3), 4)".to_string()));
+        assert_eq!(tree("2sin(pi)"), Ok("(2 * sin(pi))".to_string()));
+        assert_eq!(tree("f()"), Ok("f()".to_string()));
+        assert_eq!(tree("f(x, 1)"), Ok("f(x, 1)".to_string()));
+    }
+
+    #[test]
+    fn parses_statements() {
+        assert!(matches!(statement("x = 1 + 2"), Ok(Statement::Assignment(name, _)) if name == "x"));
+        assert!(matches!(
+            statement("f(a, b) = a + b"),
+            Ok(Statement::Definition(name, parameters, _)) if name == "f" && parameters == ["a", "b"]
+        ));
+        assert!(matches!(statement("f() = 1"), Ok(Statement::Definition(_, parameters, _)) if parameters.is_empty()));
+        assert!(matches!(statement("f(a, a) = a").err(), Some(EvalError::UnexpectedToken)));
+        assert_eq!(statement("pi = 3").err(), Some(EvalError::ReadOnly("pi".to_string())));
+        assert_eq!(statement("sin(x) = x").err(), Some(EvalError::ReadOnly("sin".to_string())));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(tree(""), Err(EvalError::EmptyExpression));
+        assert_eq!(tree("(1 + 2"), Err(EvalError::UnbalancedParentheses));
+        assert_eq!(tree("1 + 2)"), Err(EvalError::UnbalancedParentheses));
+        assert_eq!(tree("max(1, 2"), Err(EvalError::UnbalancedParentheses));
+        assert_eq!(tree("1 +"), Err(EvalError::TrailingOperator));
+        assert_eq!(tree("sin"), Err(EvalError::TrailingOperator));
+        assert_eq!(tree("* 2"), Err(EvalError::UnexpectedToken));
+        assert_eq!(tree("sin 2"), Err(EvalError::UnexpectedToken));
+        assert_eq!(tree("1 , 2"), Err(EvalError::UnexpectedToken));
+    }
+
+    #[test]
+    fn limits_the_nesting() {
+        let parentheses = format!("{}1{}", "(".repeat(5000), ")".repeat(5000));
+        assert_eq!(tree(&parentheses), Err(EvalError::NestingLimit));
+        assert_eq!(tree(&format!("{}1", "-".repeat(20_000))), Err(EvalError::NestingLimit));
+        assert_eq!(tree(&vec!["1"; 5000].join(" + ")), Err(EvalError::NestingLimit));
+        assert!(tree(&format!("{}1{}", "(".repeat(100), ")".repeat(100))).is_ok());
+        assert!(tree(&vec!["1"; 100].join(" + ")).is_ok());
+    }
+}
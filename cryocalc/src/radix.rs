@@ -0,0 +1,234 @@
+use crate::{calculator::Calculator, types::AngleUnit, types::Token, types::Operator, types::WordSize};
+
+use crate::error::EvalError;
+use crate::panels;
+
+use iced::{
+    widget::{button, column, pick_list, row, text_input, Column, Row, Text},
+    Element, Length,
+};
+
+/// Bases the radix selector offers, as far as digits 0-9 and A-Z go.
+const RADIXES: std::ops::RangeInclusive<u8> = 2..=36;
+
+/// Digit buttons in each row of the keypad.
+const DIGITS_PER_ROW: usize = 6;
+
+#[derive(Default)]
+pub struct RadixCalcState {
+    calculator: Calculator,
+    display_content: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ButtonPressed(Token),
+    DisplayContentChanged(String),
+    DisplayContentSubmitted,
+    RadixSelected(u8),
+    CycleOverflowPolicy,
+    CycleWordSize,
+    ToggleSigned,
+    Readout(panels::Readout),
+}
+
+impl RadixCalcState {
+    fn button<'a>(token: Token) -> Element<'a, Message> {
+        button(Text::new(token.to_string()))
+            .on_press(Message::ButtonPressed(token))
+            .width(Length::Fill)
+            .padding(16)
+            .into()
+    }
+
+    // Base-36 identifiers run past 64 bits, so there is no word to begin
+    // with.
+    pub fn new() -> Self {
+        RadixCalcState {
+            calculator: Calculator::new()
+                .with_base(36)
+                .with_word_size(WordSize::Unbounded),
+            display_content: String::new(),
+        }
+    }
+
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.calculator.set_angle_unit(unit);
+    }
+
+    pub fn expression(&self) -> Result<Vec<Token>, EvalError> {
+        self.calculator.expression()
+    }
+
+    pub fn set_expression(&mut self, expression: Result<Vec<Token>, EvalError>) {
+        self.display_content = match expression.and_then(|e| self.calculator.set_expression(e)) {
+            Ok(()) => self.calculator.get_display(),
+            Err(error) => error.to_string(),
+        };
+    }
+
+    pub fn calculator(&self) -> &Calculator {
+        &self.calculator
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::ButtonPressed(token) => {
+                if token.is_valid_for_base(self.calculator.base()) {
+                    self.calculator.add_token(token.clone());
+                    if let Token::Equals = token {
+                        self.display_content = match self.calculator.evaluate() {
+                            Ok(result) => self.calculator.format_number(&result),
+                            Err(error) => error.to_string(),
+                        };
+                    } else {
+                        self.display_content = self.calculator.get_display();
+                    }
+                }
+            }
+            Message::DisplayContentChanged(content) => self.display_content = content,
+            Message::DisplayContentSubmitted => {
+                match self.calculator.evaluate_input(&self.display_content) {
+                    Ok(Some(result)) => self.display_content = self.calculator.format_number(&result),
+                    // A function definition stays in the display as typed.
+                    Ok(None) => {}
+                    Err(error) => self.display_content = error.to_string(),
+                }
+            }
+            Message::RadixSelected(radix) => {
+                self.calculator.set_base(radix);
+                self.display_content = self.calculator.get_display();
+            }
+            Message::CycleOverflowPolicy => {
+                let policy = self.calculator.overflow_policy().next();
+                self.calculator.set_overflow_policy(policy);
+            }
+            Message::CycleWordSize => {
+                let word_size = self.calculator.word_size().next();
+                self.calculator.set_word_size(word_size);
+                self.display_content = self.calculator.get_display();
+            }
+            Message::ToggleSigned => {
+                let signed = !self.calculator.is_signed();
+                self.calculator.set_signed(signed);
+                self.display_content = self.calculator.get_display();
+            }
+            // Copying and switching modes are up to the application.
+            Message::Readout(_) => {}
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let space = 5;
+        row![
+            column![
+                column![
+                    text_input("...", &self.display_content)
+                        .padding(space)
+                        .on_input(Message::DisplayContentChanged)
+                        .on_submit(Message::DisplayContentSubmitted),
+                    row![
+                        Text::new("Base"),
+                        pick_list(
+                            RADIXES.collect::<Vec<u8>>(),
+                            Some(self.calculator.base()),
+                            Message::RadixSelected,
+                        )
+                        .padding(space),
+                        button(Text::new(format!(
+                            "Overflow: {}",
+                            self.calculator.overflow_policy()
+                        )))
+                        .on_press(Message::CycleOverflowPolicy)
+                        .padding(space),
+                        button(Text::new(self.calculator.word_size().to_string()))
+                            .on_press(Message::CycleWordSize)
+                            .padding(space),
+                        button(Text::new(if self.calculator.is_signed() {
+                            "Signed"
+                        } else {
+                            "Unsigned"
+                        }))
+                        .on_press(Message::ToggleSigned)
+                        .padding(space),
+                    ]
+                    .spacing(space)
+                    .align_y(iced::Alignment::Center),
+                    panels::readout(&self.calculator, Message::Readout),
+                ]
+                .spacing(space)
+                .padding(15),
+                self.keypad(),
+            ],
+            panels::variables(&self.calculator, Message::ButtonPressed),
+        ]
+        .into()
+    }
+
+    // The operators of the other integer modes, then as many digit buttons
+    // as the base has.
+    fn keypad(&self) -> Element<'_, Message> {
+        let space = 5;
+        let digits: Vec<u8> = (0..self.calculator.base()).collect();
+        let digit_rows = digits.chunks(DIGITS_PER_ROW).map(|chunk| {
+            Row::with_children(chunk.iter().map(|&d| RadixCalcState::button(Token::Digit(d))))
+                .spacing(space * 2)
+                .padding(space)
+                .into()
+        });
+        column![
+            row![
+                RadixCalcState::button(Token::ClearScreen),
+                RadixCalcState::button(Token::LeftParenthesis),
+                RadixCalcState::button(Token::RightParenthesis),
+                RadixCalcState::button(Token::Operator(Operator::Division)),
+                RadixCalcState::button(Token::ClearToken),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                RadixCalcState::button(Token::Operator(Operator::Remainder)),
+                RadixCalcState::button(Token::Operator(Operator::Modulo)),
+                RadixCalcState::button(Token::Operator(Operator::EuclideanDivision)),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                RadixCalcState::button(Token::Operator(Operator::And)),
+                RadixCalcState::button(Token::Operator(Operator::Or)),
+                RadixCalcState::button(Token::Operator(Operator::Xor)),
+                RadixCalcState::button(Token::Not),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                RadixCalcState::button(Token::Operator(Operator::Nand)),
+                RadixCalcState::button(Token::Operator(Operator::Nor)),
+                RadixCalcState::button(Token::Operator(Operator::RotateLeft)),
+                RadixCalcState::button(Token::Operator(Operator::RotateRight)),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                RadixCalcState::button(Token::Operator(Operator::ShiftLeft)),
+                RadixCalcState::button(Token::Operator(Operator::LogicalShiftRight)),
+                RadixCalcState::button(Token::Operator(Operator::ArithmeticShiftRight)),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            Column::with_children(digit_rows),
+            row![
+                RadixCalcState::button(Token::ToggleSign),
+                RadixCalcState::button(Token::Operator(Operator::Addition)),
+                RadixCalcState::button(Token::Operator(Operator::Subtraction)),
+                RadixCalcState::button(Token::Operator(Operator::Multiplication)),
+                RadixCalcState::button(Token::Operator(Operator::Power)),
+                RadixCalcState::button(Token::Equals),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+        ]
+        .padding(10)
+        .into()
+    }
+}
@@ -0,0 +1,426 @@
+// rational.rs
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use num_bigint::BigInt;
+use num_integer::Integer as _;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::types::RoundingMode;
+
+/// An exact fraction of two big integers, always kept in lowest terms with a
+/// positive denominator so that equal values compare equal field by field.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numer: BigInt,
+    denom: BigInt,
+}
+
+impl Rational {
+    /// Builds `numer / denom` in lowest terms. The denominator must not be
+    /// zero; callers check for division by zero before getting here.
+    pub fn new(numer: BigInt, denom: BigInt) -> Self {
+        assert!(!denom.is_zero(), "zero denominator");
+        let gcd = numer.gcd(&denom);
+        let (mut numer, mut denom) = (numer / &gcd, denom / gcd);
+        if denom.is_negative() {
+            numer = -numer;
+            denom = -denom;
+        }
+        Rational { numer, denom }
+    }
+
+    pub fn from_integer(n: BigInt) -> Self {
+        Rational {
+            numer: n,
+            denom: BigInt::one(),
+        }
+    }
+
+    pub fn numer(&self) -> &BigInt {
+        &self.numer
+    }
+
+    pub fn denom(&self) -> &BigInt {
+        &self.denom
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denom.is_one()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numer.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.numer.is_negative()
+    }
+
+    pub fn abs(&self) -> Self {
+        Rational {
+            numer: self.numer.abs(),
+            denom: self.denom.clone(),
+        }
+    }
+
+    /// The integer part, rounded toward zero.
+    pub fn to_integer(&self) -> BigInt {
+        &self.numer / &self.denom
+    }
+
+    pub fn recip(&self) -> Self {
+        Rational::new(self.denom.clone(), self.numer.clone())
+    }
+
+    /// Raises to a non-negative power. Numerator and denominator stay
+    /// coprime, so there is nothing to reduce.
+    pub fn pow(&self, exponent: u32) -> Self {
+        Rational {
+            numer: self.numer.pow(exponent),
+            denom: self.denom.pow(exponent),
+        }
+    }
+
+    /// The exact value of a finite double, which is always a fraction with a
+    /// power of two below. Infinities and NaN have no such value.
+    pub fn from_f64(x: f64) -> Option<Self> {
+        if !x.is_finite() {
+            return None;
+        }
+        let bits = x.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = bits & ((1 << 52) - 1);
+        // Subnormals lack the implicit leading one but share the exponent of
+        // the smallest normal numbers.
+        let (mantissa, exponent) = match exponent {
+            0 => (fraction, -1074),
+            _ => (fraction | (1 << 52), exponent - 1075),
+        };
+        let mantissa = if x.is_sign_negative() {
+            -BigInt::from(mantissa)
+        } else {
+            BigInt::from(mantissa)
+        };
+        Some(if exponent >= 0 {
+            Rational::from_integer(mantissa << exponent)
+        } else {
+            Rational::new(mantissa, BigInt::one() << -exponent)
+        })
+    }
+
+    /// The nearest double, or an infinity when the value is out of range.
+    pub fn to_f64(&self) -> f64 {
+        // Divide with about 64 significant bits to spare, then put the
+        // binary exponent back in two halves so neither step overflows.
+        let shift = self.denom.bits() as i64 - self.numer.bits() as i64 + 64;
+        let quotient = if shift >= 0 {
+            (&self.numer << shift) / &self.denom
+        } else {
+            &self.numer / (&self.denom << -shift)
+        };
+        let quotient = quotient.to_f64().unwrap_or(f64::NAN);
+        let half = (-shift / 2).clamp(-2000, 2000) as i32;
+        let rest = (-shift - half as i64).clamp(-2000, 2000) as i32;
+        quotient * 2f64.powi(half) * 2f64.powi(rest)
+    }
+
+    /// Power of ten of the leading digit: 2 for 345 and -2 for 0.0345. Zero
+    /// has no leading digit and reports 0.
+    pub fn decimal_exponent(&self) -> i64 {
+        if self.is_zero() {
+            return 0;
+        }
+        // The digit counts give a guess that is off by at most one.
+        let abs = self.abs();
+        let digits = |n: &BigInt| n.to_string().trim_start_matches('-').len() as i64;
+        let mut exponent = digits(&self.numer) - digits(&self.denom);
+        if abs < Rational::power_of_ten(exponent) {
+            exponent -= 1;
+        }
+        exponent
+    }
+
+    /// 10 raised to a power known to be modest, such as one derived from
+    /// the precision.
+    pub fn power_of_ten(exponent: i64) -> Self {
+        Rational::checked_power_of_ten(exponent).expect("power of ten out of range")
+    }
+
+    /// 10 raised to any integer power, or `None` when the exponent is too
+    /// large to count digits with.
+    pub fn checked_power_of_ten(exponent: i64) -> Option<Self> {
+        let power = BigInt::from(10).pow(u32::try_from(exponent.unsigned_abs()).ok()?);
+        Some(if exponent >= 0 {
+            Rational::from_integer(power)
+        } else {
+            Rational::new(BigInt::one(), power)
+        })
+    }
+
+    /// Rounds to a whole number in the given mode.
+    pub fn round(&self, mode: RoundingMode) -> BigInt {
+        let floor = self.numer.div_floor(&self.denom);
+        let fraction = self.clone() - Rational::from_integer(floor.clone());
+        if fraction.is_zero() {
+            return floor;
+        }
+        // Against one half: twice the fraction's numerator against its
+        // denominator.
+        let half = (&fraction.numer << 1u8).cmp(&fraction.denom);
+        let up = match mode {
+            RoundingMode::Floor => false,
+            RoundingMode::Ceiling => true,
+            RoundingMode::TowardZero => self.is_negative(),
+            RoundingMode::HalfUp => match half {
+                Ordering::Equal => !self.is_negative(),
+                _ => half == Ordering::Greater,
+            },
+            RoundingMode::HalfEven => match half {
+                Ordering::Equal => floor.is_odd(),
+                _ => half == Ordering::Greater,
+            },
+        };
+        if up { floor + 1 } else { floor }
+    }
+
+    /// Rounds to `places` digits after the decimal point; a negative count
+    /// rounds to tens, hundreds and so on.
+    pub fn round_places(&self, places: i64, mode: RoundingMode) -> Self {
+        self.checked_round_places(places, mode).expect("places out of range")
+    }
+
+    /// `round_places` for a count of places that came from the user, which
+    /// may be too large to scale by.
+    pub fn checked_round_places(&self, places: i64, mode: RoundingMode) -> Option<Self> {
+        let scale = Rational::checked_power_of_ten(places)?;
+        Some(Rational::from_integer((self.clone() * scale.clone()).round(mode)) / scale)
+    }
+
+    /// Rounds to `digits` significant decimal digits.
+    pub fn round_significant(&self, digits: u32, mode: RoundingMode) -> Self {
+        self.round_places(digits as i64 - 1 - self.decimal_exponent(), mode)
+    }
+
+    /// Writes a value that has a finite decimal expansion, switching to
+    /// scientific notation when the leading digit sits at or past
+    /// `max_exponent` places before the point or more than six after it.
+    pub fn to_scientific_string(&self, max_exponent: i64) -> String {
+        let exponent = self.decimal_exponent();
+        if (-6..max_exponent).contains(&exponent) {
+            return self.to_decimal_string(usize::MAX);
+        }
+        let mantissa = self.clone() / Rational::power_of_ten(exponent);
+        format!("{}e{}", mantissa.to_decimal_string(usize::MAX), exponent)
+    }
+
+    /// Writes the value as a decimal, putting a repeating block of digits in
+    /// brackets: 1/6 becomes `0.1[6]`. Expansions that neither end nor
+    /// repeat within `max_digits` are cut short with an ellipsis.
+    pub fn to_decimal_string(&self, max_digits: usize) -> String {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let whole = self.numer.abs() / &self.denom;
+        let mut remainder = self.numer.abs() % &self.denom;
+        if remainder.is_zero() {
+            return format!("{}{}", sign, whole);
+        }
+
+        // Long division; a remainder seen before starts the repeating block.
+        let mut digits = String::new();
+        let mut seen = HashMap::new();
+        while !remainder.is_zero() && digits.len() < max_digits {
+            if let Some(&start) = seen.get(&remainder) {
+                let (fixed, repeating) = digits.split_at(start);
+                return format!("{}{}.{}[{}]", sign, whole, fixed, repeating);
+            }
+            seen.insert(remainder.clone(), digits.len());
+            remainder *= 10;
+            let digit = &remainder / &self.denom;
+            digits.push_str(&digit.to_string());
+            remainder %= &self.denom;
+        }
+        let ellipsis = if remainder.is_zero() { "" } else { "…" };
+        format!("{}{}.{}{}", sign, whole, digits, ellipsis)
+    }
+
+    /// Bits needed by the larger of numerator and denominator.
+    pub fn bits(&self) -> u64 {
+        self.numer.bits().max(self.denom.bits())
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Rational::from_integer(BigInt::zero())
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(n: BigInt) -> Self {
+        Rational::from_integer(n)
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(n: i64) -> Self {
+        Rational::from_integer(BigInt::from(n))
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+
+    fn add(self, rhs: Rational) -> Rational {
+        Rational::new(
+            self.numer * &rhs.denom + rhs.numer * &self.denom,
+            self.denom * rhs.denom,
+        )
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+
+    fn sub(self, rhs: Rational) -> Rational {
+        self + -rhs
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+
+    fn mul(self, rhs: Rational) -> Rational {
+        Rational::new(self.numer * rhs.numer, self.denom * rhs.denom)
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+
+    fn div(self, rhs: Rational) -> Rational {
+        Rational::new(self.numer * rhs.denom, self.denom * rhs.numer)
+    }
+}
+
+/// Remainder of the division truncated toward zero, like `%` on integers.
+impl Rem for Rational {
+    type Output = Rational;
+
+    fn rem(self, rhs: Rational) -> Rational {
+        let quotient = (self.clone() / rhs.clone()).to_integer();
+        self - rhs * Rational::from_integer(quotient)
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational {
+            numer: -self.numer,
+            denom: self.denom,
+        }
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.numer * &other.denom).cmp(&(&other.numer * &self.denom))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(numer: i64, denom: i64) -> Rational {
+        Rational::new(BigInt::from(numer), BigInt::from(denom))
+    }
+
+    #[test]
+    fn keeps_lowest_terms() {
+        assert_eq!(q(6, -4), q(-3, 2));
+        assert_eq!(q(-3, 2).denom(), &BigInt::from(2));
+        assert_eq!(q(1, 3) + q(1, 6), q(1, 2));
+        assert_eq!(q(1, 2) * q(2, 1), Rational::from(1));
+        assert!(q(4, 2).is_integer());
+    }
+
+    #[test]
+    fn rounds_in_every_mode() {
+        let cases = [
+            (RoundingMode::HalfEven, [2, 2, -2, 3]),
+            (RoundingMode::HalfUp, [3, 2, -3, 3]),
+            (RoundingMode::TowardZero, [2, 2, -2, 2]),
+            (RoundingMode::Floor, [2, 2, -3, 2]),
+            (RoundingMode::Ceiling, [3, 3, -2, 3]),
+        ];
+        for (mode, expected) in cases {
+            let rounded = [q(5, 2), q(21, 10), q(-5, 2), q(27, 10)].map(|x| x.round(mode));
+            assert_eq!(rounded, expected.map(BigInt::from), "{:?}", mode);
+        }
+        assert_eq!(q(125, 100).round_places(1, RoundingMode::HalfEven), q(6, 5));
+        assert_eq!(Rational::from(1250).round_places(-2, RoundingMode::HalfUp), Rational::from(1300));
+        assert_eq!(q(2, 3).round_significant(3, RoundingMode::HalfEven), q(667, 1000));
+    }
+
+    #[test]
+    fn refuses_powers_of_ten_out_of_range() {
+        assert_eq!(Rational::checked_power_of_ten(-3), Some(q(1, 1000)));
+        assert_eq!(Rational::checked_power_of_ten(1 << 32), None);
+        assert_eq!(q(5, 4).checked_round_places(1 << 32, RoundingMode::HalfEven), None);
+    }
+
+    #[test]
+    fn writes_decimals() {
+        assert_eq!(q(1, 6).to_decimal_string(64), "0.1[6]");
+        assert_eq!(q(-1, 7).to_decimal_string(64), "-0.[142857]");
+        assert_eq!(q(5, 4).to_decimal_string(64), "1.25");
+        assert_eq!(q(1, 3).to_decimal_string(0), "0.…");
+        assert_eq!(q(1, 10_000_000).to_scientific_string(50), "1e-7");
+        assert_eq!(q(15, 1).to_scientific_string(1), "1.5e1");
+        assert_eq!(q(1234, 100).to_scientific_string(50), "12.34");
+    }
+
+    #[test]
+    fn finds_the_leading_digit() {
+        assert_eq!(Rational::from(345).decimal_exponent(), 2);
+        assert_eq!(q(345, 10_000).decimal_exponent(), -2);
+        assert_eq!(Rational::from(100).decimal_exponent(), 2);
+        assert_eq!(Rational::from(99).decimal_exponent(), 1);
+    }
+
+    #[test]
+    fn converts_doubles_exactly() {
+        assert_eq!(Rational::from_f64(0.5), Some(q(1, 2)));
+        assert_eq!(Rational::from_f64(f64::INFINITY), None);
+        for x in [0.1, -2.75, 1e300, 5e-324, 123456.789] {
+            assert_eq!(Rational::from_f64(x).map(|r| r.to_f64()), Some(x));
+        }
+    }
+
+    #[test]
+    fn takes_remainders_toward_zero() {
+        assert_eq!(Rational::from(7) % Rational::from(-3), Rational::from(1));
+        assert_eq!(Rational::from(-7) % Rational::from(3), Rational::from(-1));
+        assert_eq!(q(7, 2) % Rational::from(1), q(1, 2));
+    }
+}
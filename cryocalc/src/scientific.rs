@@ -0,0 +1,271 @@
+use crate::{calculator::Calculator, types::AngleUnit, types::ArithmeticMode, types::Function, types::Token, types::Operator};
+
+use crate::constants::{self, Constant};
+use crate::error::EvalError;
+use crate::panels;
+use crate::theme::{MyTheme, ButtonClass};
+use crate::types::RoundingMode;
+
+use iced::{
+    widget::{button, column, row, scrollable, text_input, Column, Row, Text},
+    Element, Length,
+};
+
+use button::Catalog;
+
+#[derive(Default)]
+pub struct SciCalcState {
+    calculator: Calculator,
+    display_content: String,
+    show_constants: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ButtonPressed(Token),
+    DisplayContentChanged(String),
+    DisplayContentSubmitted,
+    CycleArithmetic,
+    ToggleConstants,
+    Readout(panels::Readout),
+}
+
+impl SciCalcState {
+    fn button<'a>(token: Token) -> Element<'a, Message> {
+        let my_theme = MyTheme;
+        button(Text::new(token.to_string()))
+            .on_press(Message::ButtonPressed(token))
+            .width(Length::Fill)
+            .style(move |_theme, status| my_theme.style(&ButtonClass::Primary, status))
+            .padding(16)
+            .into()
+    }
+
+    fn function_button<'a>(function: Function) -> Element<'a, Message> {
+        SciCalcState::button(Token::Function(function))
+    }
+
+    fn constant_button<'a>(constant: &'static Constant) -> Element<'a, Message> {
+        SciCalcState::button(Token::Constant(constant))
+    }
+
+    // One line of the constants browser; pressing it enters the constant.
+    fn constant_entry<'a>(constant: &'static Constant) -> Element<'a, Message> {
+        let value = constant
+            .value(12)
+            .round_significant(12, RoundingMode::HalfEven)
+            .to_scientific_string(12);
+        let units = if constant.units.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", constant.units)
+        };
+        button(column![
+            Text::new(format!("{} = {}{}", constant.name, value, units)),
+            Text::new(constant.description).size(12),
+        ])
+        .on_press(Message::ButtonPressed(Token::Constant(constant)))
+        .width(Length::Fill)
+        .padding(8)
+        .into()
+    }
+
+    pub fn new() -> Self {
+        SciCalcState {
+            calculator: Calculator::new().with_arithmetic(ArithmeticMode::Float),
+            display_content: String::new(),
+            show_constants: false,
+        }
+    }
+
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.calculator.set_angle_unit(unit);
+    }
+
+    pub fn expression(&self) -> Result<Vec<Token>, EvalError> {
+        self.calculator.expression()
+    }
+
+    pub fn set_expression(&mut self, expression: Result<Vec<Token>, EvalError>) {
+        self.display_content = match expression.and_then(|e| self.calculator.set_expression(e)) {
+            Ok(()) => self.calculator.get_display(),
+            Err(error) => error.to_string(),
+        };
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::ButtonPressed(token) => {
+                self.show_constants = false;
+                self.calculator.add_token(token.clone());
+                if let Token::Equals = token {
+                    self.display_content = match self.calculator.evaluate() {
+                        Ok(result) => self.calculator.format_number(&result),
+                        Err(error) => error.to_string(),
+                    };
+                } else {
+                    self.display_content = self.calculator.get_display();
+                }
+            }
+            Message::DisplayContentChanged(content) => self.display_content = content,
+            Message::DisplayContentSubmitted => {
+                match self.calculator.evaluate_input(&self.display_content) {
+                    Ok(Some(result)) => self.display_content = self.calculator.format_number(&result),
+                    // A function definition stays in the display as typed.
+                    Ok(None) => {}
+                    Err(error) => self.display_content = error.to_string(),
+                }
+            }
+            Message::CycleArithmetic => {
+                // Machine words make no sense for transcendental functions,
+                // so the cycle skips integer arithmetic.
+                let arithmetic = match self.calculator.arithmetic().next() {
+                    ArithmeticMode::Integer => ArithmeticMode::Integer.next(),
+                    arithmetic => arithmetic,
+                };
+                self.calculator.set_arithmetic(arithmetic);
+                self.display_content = self.calculator.get_display();
+            }
+            Message::ToggleConstants => self.show_constants = !self.show_constants,
+            // Copying and switching modes are up to the application.
+            Message::Readout(_) => {}
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let space = 5;
+        let keypad = if self.show_constants {
+            scrollable(
+                Column::with_children(constants::CONSTANTS.iter().map(SciCalcState::constant_entry))
+                    .spacing(space)
+                    .padding(10),
+            )
+            .height(Length::Fill)
+            .into()
+        } else {
+            SciCalcState::keypad()
+        };
+        row![
+            column![
+                column![
+                    text_input("...", &self.display_content)
+                        .padding(space)
+                        .on_input(Message::DisplayContentChanged)
+                        .on_submit(Message::DisplayContentSubmitted),
+                    row![
+                        button(Text::new(format!(
+                            "Arithmetic: {}",
+                            self.calculator.arithmetic()
+                        )))
+                        .on_press(Message::CycleArithmetic)
+                        .padding(space),
+                        button(Text::new(if self.show_constants { "Keypad" } else { "Constants" }))
+                            .on_press(Message::ToggleConstants)
+                            .padding(space),
+                    ]
+                    .spacing(space),
+                    panels::readout(&self.calculator, Message::Readout),
+                ]
+                .spacing(space)
+                .padding(15),
+                keypad,
+            ]
+            .width(Length::Fill)
+            .height(Length::Fill),
+            panels::variables(&self.calculator, Message::ButtonPressed),
+        ]
+        .into()
+    }
+
+    fn keypad<'a>() -> Element<'a, Message> {
+        let space = 5;
+        column![
+            // The mathematical constants get keys; the physical ones are
+            // in the browser.
+            Row::with_children(
+                constants::CONSTANTS
+                    .iter()
+                    .filter(|constant| !constant.is_exact())
+                    .map(SciCalcState::constant_button),
+            )
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                SciCalcState::function_button(Function::Sin),
+                SciCalcState::function_button(Function::Cos),
+                SciCalcState::function_button(Function::Tan),
+                SciCalcState::function_button(Function::Ln),
+                SciCalcState::function_button(Function::Log10),
+                SciCalcState::function_button(Function::Log2),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                SciCalcState::function_button(Function::Asin),
+                SciCalcState::function_button(Function::Acos),
+                SciCalcState::function_button(Function::Atan),
+                SciCalcState::function_button(Function::Exp),
+                SciCalcState::function_button(Function::Sqrt),
+                SciCalcState::function_button(Function::Cbrt),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                SciCalcState::function_button(Function::Abs),
+                SciCalcState::function_button(Function::Floor),
+                SciCalcState::function_button(Function::Ceil),
+                SciCalcState::function_button(Function::Round),
+                SciCalcState::function_button(Function::Min),
+                SciCalcState::function_button(Function::Max),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                SciCalcState::button(Token::ClearScreen),
+                SciCalcState::button(Token::LeftParenthesis),
+                SciCalcState::button(Token::Comma),
+                SciCalcState::button(Token::RightParenthesis),
+                SciCalcState::button(Token::ClearToken),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                SciCalcState::button(Token::Digit(7)),
+                SciCalcState::button(Token::Digit(8)),
+                SciCalcState::button(Token::Digit(9)),
+                SciCalcState::button(Token::Operator(Operator::Division)),
+                SciCalcState::button(Token::Operator(Operator::Power)),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                SciCalcState::button(Token::Digit(4)),
+                SciCalcState::button(Token::Digit(5)),
+                SciCalcState::button(Token::Digit(6)),
+                SciCalcState::button(Token::Operator(Operator::Multiplication)),
+                SciCalcState::button(Token::Operator(Operator::Modulo)),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                SciCalcState::button(Token::Digit(1)),
+                SciCalcState::button(Token::Digit(2)),
+                SciCalcState::button(Token::Digit(3)),
+                SciCalcState::button(Token::Operator(Operator::Subtraction)),
+                SciCalcState::button(Token::ToggleSign),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+            row![
+                SciCalcState::button(Token::Digit(0)),
+                SciCalcState::button(Token::DecimalPoint),
+                SciCalcState::button(Token::Equals),
+                SciCalcState::button(Token::Operator(Operator::Addition)),
+            ]
+            .spacing(space * 2)
+            .padding(space),
+        ]
+        .padding(10)
+        .into()
+    }
+}
@@ -5,6 +5,7 @@ use iced::{Background, Color};
 pub struct MyTheme;
 
 #[derive(Debug, Default)]
+#[allow(dead_code)]
 pub enum ButtonClass {
     #[default]
     Primary,
@@ -1,25 +1,392 @@
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
 use std::fmt;
+use std::ops::RangeInclusive;
 
-#[derive(Default, Clone)]
+use crate::constants::Constant;
+use crate::rational::Rational;
+
+/// Whole numbers, as typed digit by digit and as held by a machine word.
+pub type Integer = BigInt;
+
+/// Numbers as they travel through the token stream and the evaluator.
+pub type Value = Rational;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CalculatorMode {
     #[default]
     Decimal,
+    Scientific,
+    Complex,
     Binary,
+    Octal,
     Hex,
+    Radix,
 }
 
-#[derive(Clone)]
+/// What integer arithmetic does when a result does not fit.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    #[default]
+    Checked,
+    Wrapping,
+    Saturating,
+}
+
+impl OverflowPolicy {
+    pub fn next(self) -> Self {
+        match self {
+            OverflowPolicy::Checked => OverflowPolicy::Wrapping,
+            OverflowPolicy::Wrapping => OverflowPolicy::Saturating,
+            OverflowPolicy::Saturating => OverflowPolicy::Checked,
+        }
+    }
+}
+
+impl fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            OverflowPolicy::Checked => "CHECKED",
+            OverflowPolicy::Wrapping => "WRAP",
+            OverflowPolicy::Saturating => "SAT",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// How the calculator does arithmetic. `Integer` truncates every division
+/// and works on machine words; `Rational` keeps exact fractions; `Float`
+/// rounds every step to a double, like most pocket calculators; `Decimal`
+/// rounds every step to a chosen number of significant decimal digits.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithmeticMode {
+    #[default]
+    Integer,
+    Rational,
+    Float,
+    Decimal,
+}
+
+impl ArithmeticMode {
+    pub fn next(self) -> Self {
+        match self {
+            ArithmeticMode::Integer => ArithmeticMode::Rational,
+            ArithmeticMode::Rational => ArithmeticMode::Float,
+            ArithmeticMode::Float => ArithmeticMode::Decimal,
+            ArithmeticMode::Decimal => ArithmeticMode::Integer,
+        }
+    }
+}
+
+impl fmt::Display for ArithmeticMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            ArithmeticMode::Integer => "INTEGER",
+            ArithmeticMode::Rational => "EXACT",
+            ArithmeticMode::Float => "FLOAT",
+            ArithmeticMode::Decimal => "DECIMAL",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Which way decimal arithmetic breaks ties and drops digits. The half
+/// modes round to nearest; `HalfUp` sends ties away from zero.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundingMode {
+    #[default]
+    HalfEven,
+    HalfUp,
+    TowardZero,
+    Floor,
+    Ceiling,
+}
+
+impl RoundingMode {
+    pub fn next(self) -> Self {
+        match self {
+            RoundingMode::HalfEven => RoundingMode::HalfUp,
+            RoundingMode::HalfUp => RoundingMode::TowardZero,
+            RoundingMode::TowardZero => RoundingMode::Floor,
+            RoundingMode::Floor => RoundingMode::Ceiling,
+            RoundingMode::Ceiling => RoundingMode::HalfEven,
+        }
+    }
+}
+
+impl fmt::Display for RoundingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            RoundingMode::HalfEven => "HALF-EVEN",
+            RoundingMode::HalfUp => "HALF-UP",
+            RoundingMode::TowardZero => "TO-ZERO",
+            RoundingMode::Floor => "FLOOR",
+            RoundingMode::Ceiling => "CEILING",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// How a result that is not a whole number is written out.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RationalFormat {
+    #[default]
+    Fraction,
+    Decimal,
+}
+
+impl RationalFormat {
+    pub fn next(self) -> Self {
+        match self {
+            RationalFormat::Fraction => RationalFormat::Decimal,
+            RationalFormat::Decimal => RationalFormat::Fraction,
+        }
+    }
+}
+
+impl fmt::Display for RationalFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            RationalFormat::Fraction => "FRACTION",
+            RationalFormat::Decimal => "DECIMAL",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// How complex mode shows a complex result: as `a + bi`, or as its
+/// magnitude and angle in the calculator's angle unit.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComplexFormat {
+    #[default]
+    Rectangular,
+    Polar,
+}
+
+impl ComplexFormat {
+    pub fn next(self) -> Self {
+        match self {
+            ComplexFormat::Rectangular => ComplexFormat::Polar,
+            ComplexFormat::Polar => ComplexFormat::Rectangular,
+        }
+    }
+}
+
+impl fmt::Display for ComplexFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            ComplexFormat::Rectangular => "RECT",
+            ComplexFormat::Polar => "POLAR",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Unit that trigonometric functions take their arguments in and inverse
+/// trigonometric functions give their results in.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AngleUnit {
+    #[default]
+    Radians,
+    Degrees,
+    Gradians,
+}
+
+impl AngleUnit {
+    pub fn next(self) -> Self {
+        match self {
+            AngleUnit::Radians => AngleUnit::Degrees,
+            AngleUnit::Degrees => AngleUnit::Gradians,
+            AngleUnit::Gradians => AngleUnit::Radians,
+        }
+    }
+
+    /// Size of a right angle, or `None` for radians where it is irrational.
+    pub fn right_angle(self) -> Option<i64> {
+        match self {
+            AngleUnit::Radians => None,
+            AngleUnit::Degrees => Some(90),
+            AngleUnit::Gradians => Some(100),
+        }
+    }
+
+    /// Suffix that marks a value as being in this unit, as in `30deg`.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            AngleUnit::Radians => "rad",
+            AngleUnit::Degrees => "deg",
+            AngleUnit::Gradians => "grad",
+        }
+    }
+
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        [AngleUnit::Radians, AngleUnit::Degrees, AngleUnit::Gradians]
+            .into_iter()
+            .find(|unit| unit.suffix() == suffix)
+    }
+}
+
+impl fmt::Display for AngleUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            AngleUnit::Radians => "RAD",
+            AngleUnit::Degrees => "DEG",
+            AngleUnit::Gradians => "GRAD",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Machine word that values are truncated to. `Unbounded` lifts the limit
+/// altogether and is always signed.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WordSize {
+    Byte,
+    Word,
+    Dword,
+    Qword,
+    #[default]
+    Unbounded,
+}
+
+impl WordSize {
+    pub fn next(self) -> Self {
+        match self {
+            WordSize::Byte => WordSize::Word,
+            WordSize::Word => WordSize::Dword,
+            WordSize::Dword => WordSize::Qword,
+            WordSize::Qword => WordSize::Unbounded,
+            WordSize::Unbounded => WordSize::Byte,
+        }
+    }
+
+    pub fn bits(self) -> Option<u32> {
+        match self {
+            WordSize::Byte => Some(8),
+            WordSize::Word => Some(16),
+            WordSize::Dword => Some(32),
+            WordSize::Qword => Some(64),
+            WordSize::Unbounded => None,
+        }
+    }
+
+    /// All ones across the word, or `None` when unbounded.
+    pub fn mask(self) -> Option<Integer> {
+        self.bits().map(|bits| (Integer::one() << bits) - 1)
+    }
+
+    /// Smallest and largest value a word of this size can hold.
+    pub fn range(self, signed: bool) -> Option<(Integer, Integer)> {
+        let bits = self.bits()?;
+        if signed {
+            let half = Integer::one() << (bits - 1);
+            Some((-half.clone(), half - 1))
+        } else {
+            Some((Integer::zero(), (Integer::one() << bits) - 1))
+        }
+    }
+
+    /// Keeps the low bits of `value` and reads them back as a word.
+    pub fn wrap(self, value: &Integer, signed: bool) -> Integer {
+        let Some(bits) = self.bits() else {
+            return value.clone();
+        };
+        let pattern: Integer = value & ((Integer::one() << bits) - 1);
+        if signed && pattern.bit(bits as u64 - 1) {
+            pattern - (Integer::one() << bits)
+        } else {
+            pattern
+        }
+    }
+
+    /// Short type name such as `i32` or `u8`.
+    pub fn type_name(self, signed: bool) -> String {
+        match self.bits() {
+            Some(bits) => format!("{}{}", if signed { 'i' } else { 'u' }, bits),
+            None => "int".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for WordSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            WordSize::Byte => "BYTE",
+            WordSize::Word => "WORD",
+            WordSize::Dword => "DWORD",
+            WordSize::Qword => "QWORD",
+            WordSize::Unbounded => "BIG",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Operator {
     Addition,
     Subtraction,
     Multiplication,
     Division,
+    Remainder,
+    Modulo,
+    EuclideanDivision,
+    Power,
+    And,
+    Or,
+    Xor,
+    Nand,
+    Nor,
+    ShiftLeft,
+    LogicalShiftRight,
+    ArithmeticShiftRight,
+    RotateLeft,
+    RotateRight,
+}
+
+/// Prefix operators. These never appear in a token stream; the parser
+/// produces them from operators found where an operand was expected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnaryOperator {
+    Negation,
+    Not,
+}
+
+impl Operator {
+    /// Binding strength of prefix operators such as unary minus.
+    pub const PREFIX_PRECEDENCE: u8 = 7;
+
+    /// Binding strength used by the parser; higher binds tighter. The
+    /// bitwise operators rank below arithmetic, in C's order.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operator::Or | Operator::Nor => 1,
+            Operator::Xor => 2,
+            Operator::And | Operator::Nand => 3,
+            Operator::ShiftLeft
+            | Operator::LogicalShiftRight
+            | Operator::ArithmeticShiftRight
+            | Operator::RotateLeft
+            | Operator::RotateRight => 4,
+            Operator::Addition | Operator::Subtraction => 5,
+            Operator::Multiplication
+            | Operator::Division
+            | Operator::Remainder
+            | Operator::Modulo
+            | Operator::EuclideanDivision => 6,
+            Operator::Power => 8,
+        }
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self, Operator::Power)
+    }
 }
 
 impl Token {
     pub fn is_valid_for_base(&self, base: u8) -> bool {
         match self {
-            Token::Number(n) => *n < base as i64,
+            Token::Digit(d) => *d < base,
+            Token::DecimalPoint => base == 10,
             _ => true,
         }
     }
@@ -32,17 +399,152 @@ impl fmt::Debug for Operator {
             Operator::Subtraction => "-",
             Operator::Multiplication => "*",
             Operator::Division => "/",
+            // `%` on its own is the percent key.
+            Operator::Remainder => "rem",
+            Operator::Modulo => "mod",
+            Operator::EuclideanDivision => "div",
+            Operator::Power => "^",
+            Operator::And => "AND",
+            Operator::Or => "OR",
+            Operator::Xor => "XOR",
+            Operator::Nand => "NAND",
+            Operator::Nor => "NOR",
+            Operator::ShiftLeft => "SHL",
+            Operator::LogicalShiftRight => "SHR",
+            Operator::ArithmeticShiftRight => "SAR",
+            Operator::RotateLeft => "ROL",
+            Operator::RotateRight => "ROR",
         };
         write!(f, "{}", op_str)
     }
 }
 
+/// Built-in functions callable as `name(arguments)`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Exp,
+    Ln,
+    Log10,
+    Log2,
+    Sqrt,
+    Cbrt,
+    Abs,
+    Arg,
+    Conj,
+    Re,
+    Im,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+}
+
+impl Function {
+    pub const ALL: [Function; 22] = [
+        Function::Sin,
+        Function::Cos,
+        Function::Tan,
+        Function::Asin,
+        Function::Acos,
+        Function::Atan,
+        Function::Exp,
+        Function::Ln,
+        Function::Log10,
+        Function::Log2,
+        Function::Sqrt,
+        Function::Cbrt,
+        Function::Abs,
+        Function::Arg,
+        Function::Conj,
+        Function::Re,
+        Function::Im,
+        Function::Floor,
+        Function::Ceil,
+        Function::Round,
+        Function::Min,
+        Function::Max,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Asin => "asin",
+            Function::Acos => "acos",
+            Function::Atan => "atan",
+            Function::Exp => "exp",
+            Function::Ln => "ln",
+            Function::Log10 => "log10",
+            Function::Log2 => "log2",
+            Function::Sqrt => "sqrt",
+            Function::Cbrt => "cbrt",
+            Function::Abs => "abs",
+            Function::Arg => "arg",
+            Function::Conj => "conj",
+            Function::Re => "re",
+            Function::Im => "im",
+            Function::Floor => "floor",
+            Function::Ceil => "ceil",
+            Function::Round => "round",
+            Function::Min => "min",
+            Function::Max => "max",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Function> {
+        Function::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    /// How many arguments the function accepts. `round` takes an optional
+    /// number of decimal places; `min` and `max` take any number but zero.
+    pub fn arity(self) -> RangeInclusive<usize> {
+        match self {
+            Function::Round => 1..=2,
+            Function::Min | Function::Max => 1..=usize::MAX,
+            _ => 1..=1,
+        }
+    }
+
+    /// Whether the argument is an angle, read in the calculator's unit.
+    pub fn takes_angle(self) -> bool {
+        matches!(self, Function::Sin | Function::Cos | Function::Tan)
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 #[derive(Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Token {
-    Number(i64),
+    Digit(u8),
+    Number(Value),
+    DecimalPoint,
     LeftParenthesis,
     RightParenthesis,
+    Function(Function),
+    Constant(&'static Constant),
+    // A variable, as typed or picked from the variables panel.
+    Identifier(String),
+    Comma,
+    // Unit suffix on the value before it, as in `30deg`.
+    Angle(AngleUnit),
+    // Desk-calculator percentage of the value before it.
+    Percent,
     Operator(Operator),
+    Not,
+    ToggleSign,
     Equals,
     ClearScreen,
     ClearToken,
@@ -51,10 +553,22 @@ pub enum Token {
 impl fmt::Debug for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let token_str = match self {
+            // Digits above nine are letters, up to Z in base 36.
+            Token::Digit(d) => char::from_digit(u32::from(*d), 36)
+                .map_or_else(|| d.to_string(), |c| c.to_ascii_uppercase().to_string()),
             Token::Number(n) => n.to_string(),
+            Token::DecimalPoint => ".".to_string(),
             Token::LeftParenthesis => "(".to_string(),
             Token::RightParenthesis => ")".to_string(),
+            Token::Function(function) => function.name().to_string(),
+            Token::Constant(constant) => constant.name.to_string(),
+            Token::Identifier(name) => name.clone(),
+            Token::Angle(unit) => unit.suffix().to_string(),
+            Token::Percent => "%".to_string(),
+            Token::Comma => ",".to_string(),
             Token::Operator(op) => format!("{:?}", op),
+            Token::Not => "NOT".to_string(),
+            Token::ToggleSign => "±".to_string(),
             Token::Equals => "=".to_string(),
             Token::ClearScreen => "CLEAR".to_string(),
             Token::ClearToken => "<<".to_string(),